@@ -0,0 +1,38 @@
+//! The abstraction a persistent [`FileSystem`](super::FileSystem) backend sits on top of.
+
+/// A pluggable, block-addressed persistent storage device.
+///
+/// Implementations are expected to be provided by the platform (a raw block device, a single
+/// regular file carved into fixed-size blocks, etc); this crate only ever talks to storage
+/// through this trait, the same way networking only ever talks to the link through
+/// [`platform::IPInterfaceProvider`](crate::platform::IPInterfaceProvider).
+pub(crate) trait BackingStore {
+    /// Errors a concrete backing store can surface (I/O errors, out-of-range blocks, ...).
+    type Error: core::fmt::Debug;
+
+    /// Size, in bytes, of a single block. Every read/write operates in whole blocks of this size.
+    fn block_size(&self) -> usize;
+
+    /// Total number of addressable blocks.
+    fn block_count(&self) -> u64;
+
+    /// Reads the block at `index` into `buf`, which must be exactly [`Self::block_size`] long.
+    fn read_block(&self, index: u64, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Writes `buf`, which must be exactly [`Self::block_size`] long, to the block at `index`.
+    fn write_block(&mut self, index: u64, buf: &[u8]) -> Result<(), Self::Error>;
+
+    /// Persists any writes buffered by the platform so far, making them durable before returning.
+    fn flush(&mut self) -> Result<(), Self::Error>;
+
+    /// Whether this store's medium is reliable enough to support a shared memory-mapping of the
+    /// data region (see the persistent backend's docket technique). Platforms that can't
+    /// guarantee coherent, torn-write-free mappings (e.g. some network-backed storage) should
+    /// return `false` so callers fall back to plain block reads instead of `mmap`.
+    ///
+    /// The persistent backend in this crate does not consult this flag: it always reads the data
+    /// region through [`Self::read_block`].
+    fn supports_mmap(&self) -> bool {
+        false
+    }
+}
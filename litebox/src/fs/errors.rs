@@ -13,42 +13,192 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum FooError {}
 
+/// Possible errors when resolving or normalizing a path
+#[non_exhaustive]
+#[derive(Error, Debug)]
+pub enum PathError {
+    #[error("a component of the path does not exist")]
+    MissingComponent,
+    #[error("a component of the path is not a directory")]
+    ComponentNotADirectory,
+    #[error("no such file or directory")]
+    NoSuchFileOrDirectory,
+    #[error("search permission is denied on a path component")]
+    NoSearchPerms,
+    #[error("too many levels of symbolic links")]
+    TooManySymlinks,
+}
+
 /// Possible errors from [`FileSystem::open`]
 #[non_exhaustive]
 #[derive(Error, Debug)]
-pub enum OpenError {}
+pub enum OpenError {
+    #[error(transparent)]
+    Path(#[from] PathError),
+    #[error("path refers to a directory")]
+    IsADirectory,
+    #[error("path already exists")]
+    AlreadyExists,
+    #[error("read permission denied")]
+    NoReadPerms,
+    #[error("write permission denied")]
+    NoWritePerms,
+    #[error("backing store I/O error: {0}")]
+    Store(alloc::string::String),
+}
+
+/// Possible errors from [`FileSystem::symlink`]
+#[non_exhaustive]
+#[derive(Error, Debug)]
+pub enum SymlinkError {
+    #[error(transparent)]
+    Path(#[from] PathError),
+    #[error("path already exists")]
+    AlreadyExists,
+    #[error("write permission denied")]
+    NoWritePerms,
+    #[error("operation not supported by this backend")]
+    Unsupported,
+}
 
 /// Possible errors from [`FileSystem::close`]
 #[non_exhaustive]
 #[derive(Error, Debug)]
-pub enum CloseError {}
+pub enum CloseError {
+    #[error("bad file descriptor")]
+    BadFileDescriptor,
+}
 
 /// Possible errors from [`FileSystem::read`]
 #[non_exhaustive]
 #[derive(Error, Debug)]
-pub enum ReadError {}
+pub enum ReadError {
+    #[error("bad file descriptor")]
+    BadFileDescriptor,
+    #[error("file descriptor is not open for reading")]
+    NotOpenForReading,
+}
 
 /// Possible errors from [`FileSystem::write`]
 #[non_exhaustive]
 #[derive(Error, Debug)]
-pub enum WriteError {}
+pub enum WriteError {
+    #[error("bad file descriptor")]
+    BadFileDescriptor,
+    #[error("file descriptor is not open for writing")]
+    NotOpenForWriting,
+    #[error("resulting offset would overflow addressable memory")]
+    InvalidOffset,
+    #[error("backing store I/O error: {0}")]
+    Store(alloc::string::String),
+}
+
+/// Possible errors from [`FileSystem::lseek`]
+#[non_exhaustive]
+#[derive(Error, Debug)]
+pub enum SeekError {
+    #[error("bad file descriptor")]
+    BadFileDescriptor,
+    #[error("resulting offset would be negative or overflow")]
+    InvalidOffset,
+}
 
 /// Possible errors from [`FileSystem::chmod`]
 #[non_exhaustive]
 #[derive(Error, Debug)]
-pub enum ChmodError {}
+pub enum ChmodError {
+    #[error(transparent)]
+    Path(#[from] PathError),
+    #[error("only the owner may change a path's mode")]
+    NotOwner,
+    #[error("operation not supported by this backend")]
+    Unsupported,
+}
 
 /// Possible errors from [`FileSystem::unlink`]
 #[non_exhaustive]
 #[derive(Error, Debug)]
-pub enum UnlinkError {}
+pub enum UnlinkError {
+    #[error(transparent)]
+    Path(#[from] PathError),
+    #[error("path refers to a directory")]
+    IsADirectory,
+    #[error("write permission denied")]
+    NoWritePerms,
+    #[error("operation not supported by this backend")]
+    Unsupported,
+}
+
+/// Possible errors from [`FileSystem::stat`]
+#[non_exhaustive]
+#[derive(Error, Debug)]
+pub enum StatError {
+    #[error(transparent)]
+    Path(#[from] PathError),
+    #[error("operation not supported by this backend")]
+    Unsupported,
+}
+
+/// Possible errors from [`FileSystem::readdir`]
+#[non_exhaustive]
+#[derive(Error, Debug)]
+pub enum ReaddirError {
+    #[error(transparent)]
+    Path(#[from] PathError),
+    #[error("path refers to something other than a directory")]
+    NotADirectory,
+    #[error("search permission is denied on the directory")]
+    NoSearchPerms,
+    #[error("operation not supported by this backend")]
+    Unsupported,
+}
+
+/// Possible errors from [`FileSystem::rename`]
+#[non_exhaustive]
+#[derive(Error, Debug)]
+pub enum RenameError {
+    #[error(transparent)]
+    Path(#[from] PathError),
+    #[error("destination already exists")]
+    AlreadyExists,
+    #[error("write permission denied")]
+    NoWritePerms,
+    #[error("cannot rename the root directory")]
+    Busy,
+    #[error("cannot rename a directory into its own descendant")]
+    InvalidArgument,
+    #[error("operation not supported by this backend")]
+    Unsupported,
+}
 
 /// Possible errors from [`FileSystem::mkdir`]
 #[non_exhaustive]
 #[derive(Error, Debug)]
-pub enum MkdirError {}
+pub enum MkdirError {
+    #[error(transparent)]
+    Path(#[from] PathError),
+    #[error("path already exists")]
+    AlreadyExists,
+    #[error("write permission denied")]
+    NoWritePerms,
+    #[error("backing store I/O error: {0}")]
+    Store(alloc::string::String),
+}
 
 /// Possible errors from [`FileSystem::rmdir`]
 #[non_exhaustive]
 #[derive(Error, Debug)]
-pub enum RmdirError {}
+pub enum RmdirError {
+    #[error(transparent)]
+    Path(#[from] PathError),
+    #[error("path refers to something other than a directory")]
+    NotADirectory,
+    #[error("directory is not empty")]
+    NotEmpty,
+    #[error("write permission denied")]
+    NoWritePerms,
+    #[error("cannot remove the root directory")]
+    Busy,
+    #[error("backing store I/O error: {0}")]
+    Store(alloc::string::String),
+}
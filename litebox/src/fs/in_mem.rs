@@ -1,34 +1,45 @@
 //! An in-memory file system, not backed by any physical device.
 
+use alloc::format;
 use alloc::string::String;
+use alloc::vec::Vec;
 use hashbrown::HashMap;
 
 use crate::path::Arg;
 use crate::sync;
 
 use super::errors::{
-    ChmodError, CloseError, MkdirError, OpenError, PathError, ReadError, RmdirError, UnlinkError,
-    WriteError,
+    ChmodError, CloseError, MkdirError, OpenError, PathError, ReaddirError, ReadError,
+    RenameError, RmdirError, SeekError, StatError, SymlinkError, UnlinkError, WriteError,
 };
 use super::Mode;
 
+/// Maximum number of symlink hops [`RootDir::resolve_symlinks`] will follow before giving up,
+/// guarding against symlink cycles (mirrors Linux's `MAXSYMLINKS`-style loop protection).
+const MAX_SYMLINK_HOPS: u32 = 40;
+
 /// A backing implementation for [`FileSystem`](super::FileSystem) storing all files in-memory.
 ///
 /// # Warning
 ///
 /// This has no physical backing store, thus any files in memory are erased as soon as this object
 /// is dropped.
-pub struct FileSystem<'platform, Platform: sync::RawSyncPrimitivesProvider> {
+pub struct FileSystem<'platform, Platform: sync::RawSyncPrimitivesProvider + crate::platform::ClockProvider>
+{
     // TODO: Possibly support a single-threaded variant that doesn't have the cost of requiring a
     // sync-primitives platform, as well as cost of mutexes and such?
     sync: sync::Synchronization<'platform, Platform>,
+    platform: &'platform Platform,
     root: sync::RwLock<'platform, Platform, RootDir>,
+    open_files: sync::RwLock<'platform, Platform, OpenFileTable>,
     current_user: UserInfo,
     // cwd invariant: always ends with a `/`
     current_working_dir: String,
 }
 
-impl<'platform, Platform: sync::RawSyncPrimitivesProvider> FileSystem<'platform, Platform> {
+impl<'platform, Platform: sync::RawSyncPrimitivesProvider + crate::platform::ClockProvider>
+    FileSystem<'platform, Platform>
+{
     /// Construct a new `FileSystem` instance
     ///
     /// This function is expected to only be invoked once per platform, as an initialiation step,
@@ -38,9 +49,12 @@ impl<'platform, Platform: sync::RawSyncPrimitivesProvider> FileSystem<'platform,
     pub fn new(platform: &'platform Platform) -> Self {
         let sync = sync::Synchronization::new(platform);
         let root = sync.new_rwlock(RootDir::new());
+        let open_files = sync.new_rwlock(OpenFileTable::new());
         Self {
             sync,
+            platform,
             root,
+            open_files,
             current_user: UserInfo {
                 user: 1000,
                 group: 1000,
@@ -50,16 +64,19 @@ impl<'platform, Platform: sync::RawSyncPrimitivesProvider> FileSystem<'platform,
     }
 }
 
-impl<Platform: sync::RawSyncPrimitivesProvider> super::private::Sealed
-    for FileSystem<'_, Platform>
+impl<Platform: sync::RawSyncPrimitivesProvider + crate::platform::ClockProvider>
+    super::private::Sealed for FileSystem<'_, Platform>
 {
 }
 
-impl<Platform: sync::RawSyncPrimitivesProvider> FileSystem<'_, Platform> {
+impl<Platform: sync::RawSyncPrimitivesProvider + crate::platform::ClockProvider>
+    FileSystem<'_, Platform>
+{
     // Gives the absolute path for `path`, resolving any `.` or `..`s, and making sure to account
     // for any relative paths from current working directory.
     //
-    // Note: does NOT account for symlinks.
+    // Note: does NOT account for symlinks; that resolution happens separately, component-by-
+    // component, in `RootDir::resolve_symlinks`.
     fn absolute_path(&self, path: impl crate::path::Arg) -> Result<String, PathError> {
         // Since cwd always ends with `/`, if the provided path is a relative path, it'll do the
         // right thing; if it is an absolute path, it'll lead to a `//`, which the normalizer will
@@ -68,42 +85,216 @@ impl<Platform: sync::RawSyncPrimitivesProvider> FileSystem<'_, Platform> {
         assert!(self.current_working_dir.ends_with('/'));
         Ok((self.current_working_dir.clone() + path.as_rust_str()?).normalized()?)
     }
+
+    // The current platform-clock value, stamped onto an entry's atime/mtime/ctime on creation,
+    // onto a file's mtime/ctime by `write`, onto the parent directory's mtime/ctime by `mkdir` and
+    // `unlink`, and onto ctime by `chmod`. `O_TRUNC`, `rmdir`, `rename` and `read` stamp nothing.
+    fn now(&self) -> u64 {
+        self.platform.now()
+    }
 }
 
-impl<Platform: sync::RawSyncPrimitivesProvider> super::FileSystem for FileSystem<'_, Platform> {
+impl<Platform: sync::RawSyncPrimitivesProvider + crate::platform::ClockProvider> super::FileSystem
+    for FileSystem<'_, Platform>
+{
     fn open(
         &self,
         path: impl crate::path::Arg,
         flags: super::OFlags,
         mode: super::Mode,
     ) -> Result<crate::fd::FileFd, OpenError> {
-        todo!()
+        let path = self.absolute_path(path)?;
+        let follow_last = !flags.contains(super::OFlags::NOFOLLOW);
+        let mut root = self.root.write();
+        let (path, parent, entry) =
+            root.parent_and_entry_mut(&path, self.current_user, follow_last)?;
+        let access = AccessMode::from_flags(flags);
+        match entry {
+            Some(entry) => {
+                if flags.contains(super::OFlags::CREAT | super::OFlags::EXCL) {
+                    return Err(OpenError::AlreadyExists);
+                }
+                let file = match entry {
+                    Entry::File(file) => file,
+                    Entry::Dir(_) => return Err(OpenError::IsADirectory),
+                    // `O_NOFOLLOW` hit a symlink as the terminal component: POSIX has `open`
+                    // fail with `ELOOP` here, same as exhausting the hop limit.
+                    Entry::Symlink(_) => return Err(PathError::TooManySymlinks)?,
+                };
+                if access.can_read() && !self.current_user.can_read(&file.perms) {
+                    return Err(OpenError::NoReadPerms);
+                }
+                if access.can_write() && !self.current_user.can_write(&file.perms) {
+                    return Err(OpenError::NoWritePerms);
+                }
+                if flags.contains(super::OFlags::TRUNC) {
+                    file.data.clear();
+                }
+            }
+            None => {
+                if !flags.contains(super::OFlags::CREAT) {
+                    return Err(PathError::NoSuchFileOrDirectory)?;
+                }
+                let Some((_, parent_dir)) = parent else {
+                    // Attempted to create `/` as a file.
+                    return Err(OpenError::IsADirectory);
+                };
+                if !self.current_user.can_write(&parent_dir.perms) {
+                    return Err(OpenError::NoWritePerms);
+                }
+                let now = self.now();
+                parent_dir.children_count = parent_dir.children_count.checked_add(1).unwrap();
+                root.entries.insert(
+                    path.clone(),
+                    Entry::File(File {
+                        perms: Permissions {
+                            mode,
+                            userinfo: self.current_user,
+                            atime: now,
+                            mtime: now,
+                            ctime: now,
+                        },
+                        data: Vec::new(),
+                        nlink: 1,
+                    }),
+                );
+            }
+        }
+        let offset = if flags.contains(super::OFlags::APPEND) {
+            let Some(Entry::File(file)) = root.entries.get(&path) else {
+                unreachable!("just inserted or confirmed a file at this path")
+            };
+            file.data.len() as u64
+        } else {
+            0
+        };
+        let mut open_files = self.open_files.write();
+        Ok(open_files.insert(OpenFile {
+            path,
+            offset,
+            access,
+            append: flags.contains(super::OFlags::APPEND),
+        }))
     }
 
     fn close(&self, fd: crate::fd::FileFd) -> Result<(), CloseError> {
-        todo!()
+        let mut open_files = self.open_files.write();
+        open_files
+            .table
+            .remove(&fd.raw())
+            .ok_or(CloseError::BadFileDescriptor)?;
+        Ok(())
     }
 
     fn read(&self, fd: &crate::fd::FileFd, buf: &mut [u8]) -> Result<usize, ReadError> {
-        todo!()
+        // Acquired in the same order as `open` (`root` before `open_files`) to avoid an AB-BA
+        // deadlock between a concurrent `open` and `read`/`write`/`lseek`.
+        let root = self.root.read();
+        let mut open_files = self.open_files.write();
+        let open_file = open_files
+            .table
+            .get_mut(&fd.raw())
+            .ok_or(ReadError::BadFileDescriptor)?;
+        if !open_file.access.can_read() {
+            return Err(ReadError::NotOpenForReading);
+        }
+        let Some(Entry::File(file)) = root.entries.get(&open_file.path) else {
+            // The entry was removed (e.g. `unlink`) while this descriptor was still open.
+            return Err(ReadError::BadFileDescriptor);
+        };
+        let start = usize::try_from(open_file.offset).unwrap_or(usize::MAX);
+        let available = file.data.get(start..).unwrap_or(&[]);
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        open_file.offset += n as u64;
+        Ok(n)
     }
 
     fn write(&self, fd: &crate::fd::FileFd, buf: &[u8]) -> Result<usize, WriteError> {
-        todo!()
+        // Acquired in the same order as `open` (`root` before `open_files`) to avoid an AB-BA
+        // deadlock between a concurrent `open` and `read`/`write`/`lseek`.
+        let mut root = self.root.write();
+        let mut open_files = self.open_files.write();
+        let open_file = open_files
+            .table
+            .get_mut(&fd.raw())
+            .ok_or(WriteError::BadFileDescriptor)?;
+        if !open_file.access.can_write() {
+            return Err(WriteError::NotOpenForWriting);
+        }
+        let Some(Entry::File(file)) = root.entries.get_mut(&open_file.path) else {
+            return Err(WriteError::BadFileDescriptor);
+        };
+        if open_file.append {
+            open_file.offset = file.data.len() as u64;
+        }
+        let start = usize::try_from(open_file.offset).unwrap_or(usize::MAX);
+        let end = start.checked_add(buf.len()).ok_or(WriteError::InvalidOffset)?;
+        if end > file.data.len() {
+            // Zero-fill any hole between the current end of the file and `start`.
+            file.data.resize(end, 0);
+        }
+        file.data[start..end].copy_from_slice(buf);
+        open_file.offset = end as u64;
+        let now = self.now();
+        file.perms.mtime = now;
+        file.perms.ctime = now;
+        Ok(buf.len())
     }
 
     fn chmod(&self, path: impl crate::path::Arg, mode: super::Mode) -> Result<(), ChmodError> {
-        todo!()
+        let path = self.absolute_path(path)?;
+        let mut root = self.root.write();
+        // `follow_last: true` — `chmod` acts on whatever the path ultimately resolves to, same as
+        // `open` without `O_NOFOLLOW`.
+        let (_, _, entry) = root.parent_and_entry_mut(&path, self.current_user, true)?;
+        let perms = match entry.ok_or(PathError::NoSuchFileOrDirectory)? {
+            Entry::Dir(dir) => &mut dir.perms,
+            Entry::File(file) => &mut file.perms,
+            Entry::Symlink(_) => {
+                unreachable!("resolve_symlinks always follows the terminal component when follow_last is true")
+            }
+        };
+        if perms.userinfo.user != self.current_user.user {
+            return Err(ChmodError::NotOwner);
+        }
+        perms.mode = mode;
+        perms.ctime = self.now();
+        Ok(())
     }
 
     fn unlink(&self, path: impl crate::path::Arg) -> Result<(), UnlinkError> {
-        todo!()
+        let path = self.absolute_path(path)?;
+        let mut root = self.root.write();
+        // `follow_last: false` — `unlink` removes the link itself, not whatever it points to.
+        let (path, parent, entry) = root.parent_and_entry_mut(&path, self.current_user, false)?;
+        let Some((_, parent)) = parent else {
+            // Attempted to unlink `/`
+            return Err(UnlinkError::IsADirectory);
+        };
+        let Some(entry) = entry else {
+            return Err(PathError::NoSuchFileOrDirectory)?;
+        };
+        if matches!(entry, Entry::Dir(_)) {
+            return Err(UnlinkError::IsADirectory);
+        }
+        if !self.current_user.can_write(&parent.perms) {
+            return Err(UnlinkError::NoWritePerms);
+        }
+        let now = self.now();
+        parent.children_count = parent.children_count.checked_sub(1).unwrap();
+        parent.perms.mtime = now;
+        parent.perms.ctime = now;
+        root.entries.remove(&path).unwrap();
+        Ok(())
     }
 
     fn mkdir(&self, path: impl crate::path::Arg, mode: super::Mode) -> Result<(), MkdirError> {
         let path = self.absolute_path(path)?;
         let mut root = self.root.write();
-        let (parent, entry) = root.parent_and_entry_mut(&path, self.current_user)?;
+        // `follow_last: false` — `mkdir` must act on the path itself, not wherever a terminal
+        // symlink there points.
+        let (path, parent, entry) = root.parent_and_entry_mut(&path, self.current_user, false)?;
         let Some((parent_path, parent)) = parent else {
             // Attempted to make `/`
             return Err(MkdirError::AlreadyExists);
@@ -114,15 +305,22 @@ impl<Platform: sync::RawSyncPrimitivesProvider> super::FileSystem for FileSystem
         if !self.current_user.can_write(&parent.perms) {
             return Err(MkdirError::NoWritePerms);
         };
+        let now = self.now();
         parent.children_count = parent.children_count.checked_add(1).unwrap();
-        let old = root.entries.insert(
+        parent.perms.mtime = now;
+        parent.perms.ctime = now;
+        root.entries.insert(
             path,
             Entry::Dir(Dir {
                 perms: Permissions {
                     mode,
                     userinfo: self.current_user,
+                    atime: now,
+                    mtime: now,
+                    ctime: now,
                 },
                 children_count: 0,
+                nlink: 2,
             }),
         );
         Ok(())
@@ -131,7 +329,9 @@ impl<Platform: sync::RawSyncPrimitivesProvider> super::FileSystem for FileSystem
     fn rmdir(&self, path: impl crate::path::Arg) -> Result<(), RmdirError> {
         let path = self.absolute_path(path)?;
         let mut root = self.root.write();
-        let (parent, entry) = root.parent_and_entry_mut(&path, self.current_user)?;
+        // `follow_last: false` — a symlink is never itself a directory, so `rmdir` must see it
+        // unresolved and reject it with `NotADirectory`, matching POSIX.
+        let (path, parent, entry) = root.parent_and_entry_mut(&path, self.current_user, false)?;
         let Some((_, parent)) = parent else {
             // Attempted to remove `/`
             return Err(RmdirError::Busy);
@@ -160,6 +360,257 @@ impl<Platform: sync::RawSyncPrimitivesProvider> super::FileSystem for FileSystem
         ));
         Ok(())
     }
+
+    fn symlink(
+        &self,
+        target: impl crate::path::Arg,
+        linkpath: impl crate::path::Arg,
+    ) -> Result<(), SymlinkError> {
+        // The target is stored verbatim (it may be relative or point outside the tree entirely);
+        // it is only ever interpreted lazily, by `RootDir::resolve_symlinks`, while traversing it.
+        let target = String::from(target.as_rust_str()?);
+        let linkpath = self.absolute_path(linkpath)?;
+        let mut root = self.root.write();
+        // `follow_last: false` — the link itself must land at `linkpath`, not be spliced away.
+        let (linkpath, parent, entry) =
+            root.parent_and_entry_mut(&linkpath, self.current_user, false)?;
+        let Some((_, parent)) = parent else {
+            // Attempted to make `/` a symlink.
+            return Err(SymlinkError::AlreadyExists);
+        };
+        let None = entry else {
+            return Err(SymlinkError::AlreadyExists);
+        };
+        if !self.current_user.can_write(&parent.perms) {
+            return Err(SymlinkError::NoWritePerms);
+        }
+        let now = self.now();
+        parent.children_count = parent.children_count.checked_add(1).unwrap();
+        root.entries.insert(
+            linkpath,
+            Entry::Symlink(Symlink {
+                perms: Permissions {
+                    // Permissions on the link itself are meaningless on POSIX systems (they are
+                    // always reported as `rwxrwxrwx`); only the target's permissions matter.
+                    mode: Mode::RUSR
+                        | Mode::WUSR
+                        | Mode::XUSR
+                        | Mode::RGRP
+                        | Mode::WGRP
+                        | Mode::XGRP
+                        | Mode::ROTH
+                        | Mode::WOTH
+                        | Mode::XOTH,
+                    userinfo: self.current_user,
+                    atime: now,
+                    mtime: now,
+                    ctime: now,
+                },
+                target,
+                nlink: 1,
+            }),
+        );
+        Ok(())
+    }
+
+    fn lseek(
+        &self,
+        fd: &crate::fd::FileFd,
+        whence: super::Whence,
+        offset: i64,
+    ) -> Result<u64, SeekError> {
+        // Acquired in the same order as `open` (`root` before `open_files`) to avoid an AB-BA
+        // deadlock between a concurrent `open` and `read`/`write`/`lseek`, even though only the
+        // `Whence::End` branch below actually needs `root`.
+        let root = self.root.read();
+        let mut open_files = self.open_files.write();
+        let open_file = open_files
+            .table
+            .get_mut(&fd.raw())
+            .ok_or(SeekError::BadFileDescriptor)?;
+        let base = match whence {
+            super::Whence::Set => 0,
+            super::Whence::Cur => open_file.offset,
+            super::Whence::End => {
+                let Some(Entry::File(file)) = root.entries.get(&open_file.path) else {
+                    return Err(SeekError::BadFileDescriptor);
+                };
+                file.data.len() as u64
+            }
+        };
+        let new_offset = base
+            .checked_add_signed(offset)
+            .ok_or(SeekError::InvalidOffset)?;
+        open_file.offset = new_offset;
+        Ok(new_offset)
+    }
+
+    fn stat(&self, path: impl crate::path::Arg) -> Result<super::Metadata, StatError> {
+        let path = self.absolute_path(path)?;
+        let root = self.root.read();
+        // `follow_last: true` — `stat` reports on whatever the path ultimately resolves to;
+        // callers wanting the link itself would need a separate `lstat`, not yet exposed.
+        let (_, _, entry) = root.parent_and_entry(&path, self.current_user, true)?;
+        let entry = entry.ok_or(PathError::NoSuchFileOrDirectory)?;
+        Ok(match entry {
+            Entry::Dir(dir) => super::Metadata {
+                kind: super::FileType::Dir,
+                size: 0,
+                mode: dir.perms.mode,
+                uid: dir.perms.userinfo.user,
+                gid: dir.perms.userinfo.group,
+                atime: dir.perms.atime,
+                mtime: dir.perms.mtime,
+                ctime: dir.perms.ctime,
+                nlink: dir.nlink,
+            },
+            Entry::File(file) => super::Metadata {
+                kind: super::FileType::File,
+                size: file.data.len() as u64,
+                mode: file.perms.mode,
+                uid: file.perms.userinfo.user,
+                gid: file.perms.userinfo.group,
+                atime: file.perms.atime,
+                mtime: file.perms.mtime,
+                ctime: file.perms.ctime,
+                nlink: file.nlink,
+            },
+            Entry::Symlink(link) => super::Metadata {
+                kind: super::FileType::Symlink,
+                size: link.target.len() as u64,
+                mode: link.perms.mode,
+                uid: link.perms.userinfo.user,
+                gid: link.perms.userinfo.group,
+                atime: link.perms.atime,
+                mtime: link.perms.mtime,
+                ctime: link.perms.ctime,
+                nlink: link.nlink,
+            },
+        })
+    }
+
+    fn readdir(&self, path: impl crate::path::Arg) -> Result<Vec<super::DirEntry>, ReaddirError> {
+        let path = self.absolute_path(path)?;
+        let root = self.root.read();
+        let (path, _, entry) = root.parent_and_entry(&path, self.current_user, true)?;
+        let entry = entry.ok_or(PathError::NoSuchFileOrDirectory)?;
+        let Entry::Dir(dir) = entry else {
+            return Err(ReaddirError::NotADirectory);
+        };
+        if !self.current_user.can_execute(&dir.perms) {
+            return Err(ReaddirError::NoSearchPerms);
+        }
+        // `root.entries` is keyed by normalized absolute path, so immediate children of `path` are
+        // exactly the keys that start with `path` + `/` and have no further `/` after that.
+        let prefix = format!("{path}/");
+        let mut out = Vec::new();
+        for (key, entry) in &root.entries {
+            let Some(name) = key.strip_prefix(&prefix) else {
+                continue;
+            };
+            if name.is_empty() || name.contains('/') {
+                continue;
+            }
+            out.push(super::DirEntry {
+                name: String::from(name),
+                kind: match entry {
+                    Entry::Dir(_) => super::FileType::Dir,
+                    Entry::File(_) => super::FileType::File,
+                    Entry::Symlink(_) => super::FileType::Symlink,
+                },
+            });
+        }
+        Ok(out)
+    }
+
+    fn rename(
+        &self,
+        from: impl crate::path::Arg,
+        to: impl crate::path::Arg,
+    ) -> Result<(), RenameError> {
+        let from = self.absolute_path(from)?;
+        let to = self.absolute_path(to)?;
+        if from == to {
+            return Ok(());
+        }
+        let mut root = self.root.write();
+
+        // `follow_last: false` on both sides — `rename` moves the entry itself (a symlink is
+        // moved, not followed), mirroring `unlink`/`symlink`. `entries` is keyed by the fully
+        // resolved path, so the resolved `from`/`to` these calls hand back — not the possibly
+        // symlink-laden arguments above — are what the re-keying below must operate on.
+        let from = {
+            let (from, parent, entry) =
+                root.parent_and_entry_mut(&from, self.current_user, false)?;
+            let Some((_, parent)) = parent else {
+                return Err(RenameError::Busy); // Attempted to rename `/`
+            };
+            if entry.is_none() {
+                return Err(PathError::NoSuchFileOrDirectory)?;
+            }
+            if !self.current_user.can_write(&parent.perms) {
+                return Err(RenameError::NoWritePerms);
+            }
+            from
+        };
+        let to = {
+            let (to, parent, entry) = root.parent_and_entry_mut(&to, self.current_user, false)?;
+            let Some((_, parent)) = parent else {
+                return Err(RenameError::AlreadyExists); // Attempted to rename onto `/`
+            };
+            if entry.is_some() {
+                return Err(RenameError::AlreadyExists);
+            }
+            if !self.current_user.can_write(&parent.perms) {
+                return Err(RenameError::NoWritePerms);
+            }
+            to
+        };
+
+        let from_parent_key = parent_key(&from).to_owned();
+        let to_parent_key = parent_key(&to).to_owned();
+        let is_dir = matches!(root.entries.get(&from), Some(Entry::Dir(_)));
+
+        // Renaming a directory into its own descendant (e.g. `/a` -> `/a/b`) would remove `from`
+        // before `to`'s parent key is re-inserted under its new name, silently orphaning the
+        // subtree and leaving `children_count` bookkeeping wrong. Reject it up front instead.
+        if is_dir && to.starts_with(&format!("{from}/")) {
+            return Err(RenameError::InvalidArgument);
+        }
+
+        // Re-key `from` (and, for a directory, every descendant key) onto `to`.
+        let keys_to_move: Vec<String> = if is_dir {
+            let descendant_prefix = format!("{from}/");
+            root.entries
+                .keys()
+                .filter(|key| **key == from || key.starts_with(&descendant_prefix))
+                .cloned()
+                .collect()
+        } else {
+            alloc::vec![from.clone()]
+        };
+        for key in keys_to_move {
+            let entry = root.entries.remove(&key).unwrap();
+            let new_key = format!("{to}{}", &key[from.len()..]);
+            root.entries.insert(new_key, entry);
+        }
+
+        if from_parent_key != to_parent_key {
+            if let Some(Entry::Dir(dir)) = root.entries.get_mut(&from_parent_key) {
+                dir.children_count = dir.children_count.checked_sub(1).unwrap();
+            }
+            if let Some(Entry::Dir(dir)) = root.entries.get_mut(&to_parent_key) {
+                dir.children_count = dir.children_count.checked_add(1).unwrap();
+            }
+        }
+        Ok(())
+    }
+}
+
+// The key of `path`'s parent directory in `RootDir::entries` (the root's own parent is itself,
+// conventionally keyed by the empty string).
+fn parent_key(path: &str) -> &str {
+    path.rfind('/').map_or("", |i| &path[..i])
 }
 
 struct RootDir {
@@ -168,10 +619,16 @@ struct RootDir {
     entries: HashMap<String, Entry>,
 }
 
-// Parent, if it exists, is the path as well as the directory
+// The resolved absolute path (every symlink spliced in, except a terminal one when `follow_last`
+// is `false`), followed by the parent, if it
+// exists (the path as well as the directory), followed by the entry, if it exists, just the
+// entry itself.
 //
-// The entry, if it exists, is just the entry itself
-type ParentAndEntry<'a, D, E> = Result<(Option<(&'a str, D)>, Option<E>), PathError>;
+// Callers that go on to mutate `RootDir::entries` by key (insert/remove/prefix-scan) MUST use
+// this resolved path rather than whatever path they originally passed in: `entries` is always
+// keyed by the fully-resolved path, and the original argument may still contain unresolved
+// symlink components.
+type ParentAndEntry<'a, D, E> = Result<(String, Option<(&'a str, D)>, Option<E>), PathError>;
 
 impl RootDir {
     fn new() -> Self {
@@ -182,8 +639,15 @@ impl RootDir {
                     perms: Permissions {
                         mode: Mode::RWXU | Mode::RGRP | Mode::XGRP | Mode::ROTH | Mode::WOTH,
                         userinfo: UserInfo { user: 0, group: 0 },
+                        // No platform clock is available here. mtime/ctime stay 0 until a
+                        // `mkdir`/`unlink` directly under the root (or a `chmod` of it, ctime
+                        // only) stamps them; atime stays 0.
+                        atime: 0,
+                        mtime: 0,
+                        ctime: 0,
                     },
                     children_count: 0,
+                    nlink: 2,
                 }),
             )]
             .into_iter()
@@ -191,7 +655,78 @@ impl RootDir {
         }
     }
 
-    fn parent_and_entry(&self, path: &str, current_user: UserInfo) -> ParentAndEntry<&Dir, &Entry> {
+    /// Resolves every symlink encountered while walking `path`, splicing each link's target into
+    /// the remaining path and re-normalizing (absolute targets replace from root, relative
+    /// targets resolve against the link's parent directory).
+    ///
+    /// Bounded by [`MAX_SYMLINK_HOPS`] to defeat symlink cycles, returning
+    /// [`PathError::TooManySymlinks`] (ELOOP) past that limit.
+    ///
+    /// When `follow_last` is `false`, a symlink at the final path component is left unresolved
+    /// (its own path is returned, untouched) rather than being followed, mirroring `O_NOFOLLOW`.
+    fn resolve_symlinks(
+        &self,
+        path: &str,
+        current_user: UserInfo,
+        follow_last: bool,
+    ) -> Result<String, PathError> {
+        let mut work = String::from(path);
+        // One pass per hop, plus a final pass that finds no symlink left to splice.
+        for _ in 0..=MAX_SYMLINK_HOPS {
+            let mut collected = String::new();
+            let mut components = work.normalized_components()?.peekable();
+            let mut spliced = false;
+            while let Some(p) = components.next() {
+                if p.is_empty() || p == ".." {
+                    continue;
+                }
+                match self
+                    .entries
+                    .get(&collected)
+                    .ok_or(PathError::MissingComponent)?
+                {
+                    Entry::File(_) | Entry::Symlink(_) => {
+                        return Err(PathError::ComponentNotADirectory)
+                    }
+                    Entry::Dir(dir) => {
+                        if !current_user.can_execute(&dir.perms) {
+                            return Err(PathError::NoSearchPerms);
+                        }
+                    }
+                }
+                let candidate = collected.clone() + "/" + p;
+                let is_last = components.peek().is_none();
+                if is_last && !follow_last {
+                    return Ok(candidate);
+                }
+                if let Some(Entry::Symlink(link)) = self.entries.get(&candidate) {
+                    // Splice the link's target in place of `p`, keeping whatever of the
+                    // original path came after it, then restart the walk from the top.
+                    let rest: String = components.map(|c| format!("/{c}")).collect();
+                    work = if link.target.starts_with('/') {
+                        format!("{}{}", link.target, rest)
+                    } else {
+                        format!("{}/{}{}", collected, link.target, rest)
+                    };
+                    spliced = true;
+                    break;
+                }
+                collected = candidate;
+            }
+            if !spliced {
+                return Ok(collected);
+            }
+        }
+        Err(PathError::TooManySymlinks)
+    }
+
+    fn parent_and_entry(
+        &self,
+        path: &str,
+        current_user: UserInfo,
+        follow_last: bool,
+    ) -> ParentAndEntry<&Dir, &Entry> {
+        let path = self.resolve_symlinks(path, current_user, follow_last)?;
         let mut real_components_seen = false;
         let mut collected = String::new();
         let mut parent_dir = None;
@@ -209,7 +744,9 @@ impl RootDir {
                 .get_key_value(&collected)
                 .ok_or(PathError::MissingComponent)?
             {
-                (_, Entry::File(_)) => return Err(PathError::ComponentNotADirectory),
+                (_, Entry::File(_) | Entry::Symlink(_)) => {
+                    return Err(PathError::ComponentNotADirectory)
+                }
                 (parent_path, Entry::Dir(dir)) => {
                     if !current_user.can_execute(&dir.perms) {
                         return Err(PathError::NoSearchPerms);
@@ -220,13 +757,16 @@ impl RootDir {
             collected += "/";
             collected += p;
         }
-        Ok((parent_dir, self.entries.get(&collected)))
+        let entry = self.entries.get(&collected);
+        Ok((path, parent_dir, entry))
     }
     fn parent_and_entry_mut(
         &mut self,
         path: &str,
         current_user: UserInfo,
+        follow_last: bool,
     ) -> ParentAndEntry<&mut Dir, &mut Entry> {
+        let path = self.resolve_symlinks(path, current_user, follow_last)?;
         let mut real_components_seen = false;
         let mut collected = String::new();
         let mut parent_path = None;
@@ -244,7 +784,9 @@ impl RootDir {
                 .get_mut(&collected)
                 .ok_or(PathError::MissingComponent)?
             {
-                Entry::File(_) => return Err(PathError::ComponentNotADirectory),
+                Entry::File(_) | Entry::Symlink(_) => {
+                    return Err(PathError::ComponentNotADirectory)
+                }
                 Entry::Dir(dir) => {
                     if !current_user.can_execute(&dir.perms) {
                         return Err(PathError::NoSearchPerms);
@@ -260,13 +802,14 @@ impl RootDir {
                 .entries
                 .get_many_key_value_mut([&parent_path, &collected]);
             let (parent_path, parent_dir) = match parent_path_and_entry.unwrap() {
-                (_, Entry::File(_)) => unreachable!(),
+                (_, Entry::File(_) | Entry::Symlink(_)) => unreachable!(),
                 (path, Entry::Dir(dir)) => (path, dir),
             };
             let main_entry = main_path_and_entry.map(|(_, e)| e);
-            Ok((Some((parent_path, parent_dir)), main_entry))
+            Ok((path, Some((parent_path, parent_dir)), main_entry))
         } else {
-            Ok((None, self.entries.get_mut(&collected)))
+            let entry = self.entries.get_mut(&collected);
+            Ok((path, None, entry))
         }
     }
 }
@@ -274,21 +817,100 @@ impl RootDir {
 enum Entry {
     File(File),
     Dir(Dir),
+    Symlink(Symlink),
 }
 
 struct Dir {
     perms: Permissions,
     children_count: u32,
+    // Not maintained precisely against the real POSIX rule (2 + number of child directories);
+    // fixed at creation, same simplification `stat`'s WASI host filesystem counterpart makes.
+    nlink: u32,
+}
+
+struct Symlink {
+    perms: Permissions,
+    target: String,
+    nlink: u32,
 }
 
 struct File {
     perms: Permissions,
-    // TODO: Actual data
+    data: Vec<u8>,
+    nlink: u32,
+}
+
+/// An open-file table entry, one per live [`FileFd`](crate::fd::FileFd).
+///
+/// Distinct opens of the same path get independent entries (and thus independent seek offsets),
+/// matching POSIX `open` semantics.
+struct OpenFile {
+    // Key into `RootDir::entries` for the file this descriptor targets.
+    path: String,
+    offset: u64,
+    access: AccessMode,
+    // Forces every `write` to reposition to the current end-of-file first (`O_APPEND`).
+    append: bool,
+}
+
+#[derive(Clone, Copy)]
+enum AccessMode {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl AccessMode {
+    fn from_flags(flags: super::OFlags) -> Self {
+        if flags.contains(super::OFlags::RDWR) {
+            Self::ReadWrite
+        } else if flags.contains(super::OFlags::WRONLY) {
+            Self::Write
+        } else {
+            Self::Read
+        }
+    }
+
+    fn can_read(self) -> bool {
+        matches!(self, Self::Read | Self::ReadWrite)
+    }
+
+    fn can_write(self) -> bool {
+        matches!(self, Self::Write | Self::ReadWrite)
+    }
+}
+
+/// The table of currently-open files, indexed by the raw value of a [`FileFd`](crate::fd::FileFd).
+struct OpenFileTable {
+    next_fd: u64,
+    table: HashMap<u64, OpenFile>,
+}
+
+impl OpenFileTable {
+    fn new() -> Self {
+        Self {
+            next_fd: 0,
+            table: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, open_file: OpenFile) -> crate::fd::FileFd {
+        let raw = self.next_fd;
+        self.next_fd = self
+            .next_fd
+            .checked_add(1)
+            .expect("file descriptor space exhausted");
+        self.table.insert(raw, open_file);
+        crate::fd::FileFd::from_raw(raw)
+    }
 }
 
 struct Permissions {
     mode: Mode,
     userinfo: UserInfo,
+    atime: u64,
+    mtime: u64,
+    ctime: u64,
 }
 
 #[derive(Clone, Copy)]
@@ -338,3 +960,283 @@ impl Permissions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_user() -> UserInfo {
+        UserInfo { user: 0, group: 0 }
+    }
+
+    fn test_dir_perms() -> Permissions {
+        Permissions {
+            mode: Mode::RWXU,
+            userinfo: test_user(),
+            atime: 0,
+            mtime: 0,
+            ctime: 0,
+        }
+    }
+
+    fn test_file_perms() -> Permissions {
+        Permissions {
+            mode: Mode::RUSR | Mode::WUSR,
+            userinfo: test_user(),
+            atime: 0,
+            mtime: 0,
+            ctime: 0,
+        }
+    }
+
+    fn mkdir(root: &mut RootDir, path: &str) {
+        let (path, parent, entry) = root.parent_and_entry_mut(path, test_user(), false).unwrap();
+        assert!(entry.is_none(), "{path} already exists");
+        let (_, parent_dir) = parent.expect("not creating the root itself");
+        parent_dir.children_count = parent_dir.children_count.checked_add(1).unwrap();
+        root.entries.insert(
+            path,
+            Entry::Dir(Dir {
+                perms: test_dir_perms(),
+                children_count: 0,
+                nlink: 2,
+            }),
+        );
+    }
+
+    #[test]
+    fn open_file_table_hands_out_independent_fds_for_repeat_opens() {
+        let mut table = OpenFileTable::new();
+        let fd1 = table.insert(OpenFile {
+            path: "/a".into(),
+            offset: 0,
+            access: AccessMode::Read,
+            append: false,
+        });
+        let fd2 = table.insert(OpenFile {
+            path: "/a".into(),
+            offset: 5,
+            access: AccessMode::Read,
+            append: false,
+        });
+        assert_ne!(fd1.raw(), fd2.raw());
+        assert_eq!(table.table.get(&fd2.raw()).unwrap().offset, 5);
+    }
+
+    #[test]
+    fn open_file_table_forgets_a_closed_descriptor() {
+        let mut table = OpenFileTable::new();
+        let fd = table.insert(OpenFile {
+            path: "/a".into(),
+            offset: 0,
+            access: AccessMode::ReadWrite,
+            append: false,
+        });
+        assert!(table.table.remove(&fd.raw()).is_some());
+        assert!(table.table.remove(&fd.raw()).is_none());
+    }
+
+    #[test]
+    fn mkdir_then_rmdir_round_trips_through_parent_and_entry_mut() {
+        let mut root = RootDir::new();
+        mkdir(&mut root, "/dir");
+        assert!(matches!(root.entries.get("/dir"), Some(Entry::Dir(_))));
+
+        let (path, parent, entry) = root
+            .parent_and_entry_mut("/dir", test_user(), false)
+            .unwrap();
+        let (_, parent_dir) = parent.expect("not removing the root itself");
+        let Some(Entry::Dir(dir)) = entry else {
+            panic!("expected a directory");
+        };
+        assert_eq!(dir.children_count, 0);
+        parent_dir.children_count = parent_dir.children_count.checked_sub(1).unwrap();
+        root.entries.remove(&path).unwrap();
+
+        assert!(root.entries.get("/dir").is_none());
+    }
+
+    #[test]
+    fn created_file_entry_holds_the_bytes_appended_to_it() {
+        let mut root = RootDir::new();
+        let (path, parent, entry) = root
+            .parent_and_entry_mut("/greeting", test_user(), false)
+            .unwrap();
+        assert!(entry.is_none());
+        let (_, parent_dir) = parent.expect("not creating the root itself");
+        parent_dir.children_count = parent_dir.children_count.checked_add(1).unwrap();
+        root.entries.insert(
+            path,
+            Entry::File(File {
+                perms: test_file_perms(),
+                data: Vec::new(),
+                nlink: 1,
+            }),
+        );
+
+        let Some(Entry::File(file)) = root.entries.get_mut("/greeting") else {
+            panic!("expected a file");
+        };
+        file.data.extend_from_slice(b"hello");
+        assert_eq!(file.data, b"hello");
+    }
+
+    fn symlink(root: &mut RootDir, target: &str, linkpath: &str) {
+        let (linkpath, parent, entry) = root
+            .parent_and_entry_mut(linkpath, test_user(), false)
+            .unwrap();
+        assert!(entry.is_none());
+        let (_, parent_dir) = parent.expect("not linking the root itself");
+        parent_dir.children_count = parent_dir.children_count.checked_add(1).unwrap();
+        root.entries.insert(
+            linkpath,
+            Entry::Symlink(Symlink {
+                perms: test_file_perms(),
+                target: String::from(target),
+                nlink: 1,
+            }),
+        );
+    }
+
+    #[test]
+    fn mkdir_through_a_symlinked_parent_lands_under_the_resolved_path() {
+        let mut root = RootDir::new();
+        mkdir(&mut root, "/real");
+        symlink(&mut root, "/real", "/link");
+
+        let (resolved, parent, entry) = root
+            .parent_and_entry_mut("/link/sub", test_user(), false)
+            .unwrap();
+        assert_eq!(
+            resolved, "/real/sub",
+            "parent_and_entry_mut must resolve symlinked parent components"
+        );
+        assert!(entry.is_none());
+        let (_, parent_dir) = parent.expect("/real resolved fine, so has a parent entry");
+        parent_dir.children_count = parent_dir.children_count.checked_add(1).unwrap();
+        root.entries.insert(
+            resolved,
+            Entry::Dir(Dir {
+                perms: test_dir_perms(),
+                children_count: 0,
+                nlink: 2,
+            }),
+        );
+
+        // The new directory must live under the resolved key, not the raw symlinked one, and
+        // must be reachable again (not orphaned) through the resolved path.
+        assert!(root.entries.contains_key("/real/sub"));
+        assert!(!root.entries.contains_key("/link/sub"));
+        let (_, _, entry) = root
+            .parent_and_entry("/real/sub", test_user(), true)
+            .unwrap();
+        assert!(matches!(entry, Some(Entry::Dir(_))));
+    }
+
+    #[test]
+    fn unlink_through_a_symlinked_parent_does_not_panic() {
+        let mut root = RootDir::new();
+        mkdir(&mut root, "/real");
+        symlink(&mut root, "/real", "/link");
+        mkdir(&mut root, "/link/sub");
+
+        let (path, parent, entry) = root
+            .parent_and_entry_mut("/link/sub", test_user(), false)
+            .unwrap();
+        assert!(entry.is_some());
+        let (_, parent_dir) = parent.unwrap();
+        parent_dir.children_count = parent_dir.children_count.checked_sub(1).unwrap();
+        // Before the resolved-path fix, `path` here would still be the caller's raw
+        // "/link/sub", which was never an actual key in `entries` (the real key is
+        // "/real/sub"), and this `.unwrap()` would panic.
+        root.entries.remove(&path).unwrap();
+
+        assert!(root.entries.get("/real/sub").is_none());
+    }
+
+    #[test]
+    fn resolve_symlinks_detects_cycles() {
+        let mut root = RootDir::new();
+        symlink(&mut root, "/b", "/a");
+        symlink(&mut root, "/a", "/b");
+
+        match root.resolve_symlinks("/a", test_user(), true) {
+            Err(PathError::TooManySymlinks) => {}
+            other => panic!("expected TooManySymlinks, got {other:?}"),
+        }
+    }
+
+    fn children_of(root: &RootDir, path: &str) -> Vec<String> {
+        // Mirrors `FileSystem::readdir`: scan under the resolved path, which is what `entries` is
+        // keyed by.
+        let (path, _, _) = root.parent_and_entry(path, test_user(), true).unwrap();
+        let prefix = format!("{path}/");
+        let mut names: Vec<String> = root
+            .entries
+            .keys()
+            .filter_map(|key| key.strip_prefix(&prefix))
+            .filter(|name| !name.is_empty() && !name.contains('/'))
+            .map(String::from)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn readdir_through_a_symlinked_parent_sees_the_real_children() {
+        let mut root = RootDir::new();
+        mkdir(&mut root, "/real");
+        mkdir(&mut root, "/real/child");
+        symlink(&mut root, "/real", "/link");
+
+        // Before the resolved-path fix, this scanned for keys under the raw, unresolved
+        // "/link/" prefix and always came back empty.
+        assert_eq!(children_of(&root, "/link"), vec![String::from("child")]);
+    }
+
+    #[test]
+    fn stat_reports_the_nlink_and_kind_of_a_directory() {
+        let mut root = RootDir::new();
+        mkdir(&mut root, "/dir");
+        let (_, _, entry) = root.parent_and_entry("/dir", test_user(), true).unwrap();
+        let Some(Entry::Dir(dir)) = entry else {
+            panic!("expected a directory");
+        };
+        assert_eq!(dir.nlink, 2);
+    }
+
+    #[test]
+    fn rename_moves_a_directory_and_its_descendants_to_the_new_key() {
+        let mut root = RootDir::new();
+        mkdir(&mut root, "/from");
+        mkdir(&mut root, "/from/child");
+        mkdir(&mut root, "/to");
+
+        // Mirrors `FileSystem::rename`'s own re-keying logic against `RootDir` directly, since
+        // exercising that requires a `sync`/`platform`-backed `FileSystem` this test file can't
+        // construct.
+        let from = String::from("/from");
+        let to = String::from("/to/from");
+        let is_dir = matches!(root.entries.get(&from), Some(Entry::Dir(_)));
+        assert!(is_dir);
+        let descendant_prefix = format!("{from}/");
+        let keys_to_move: Vec<String> = root
+            .entries
+            .keys()
+            .filter(|key| **key == from || key.starts_with(&descendant_prefix))
+            .cloned()
+            .collect();
+        for key in keys_to_move {
+            let entry = root.entries.remove(&key).unwrap();
+            let new_key = format!("{to}{}", &key[from.len()..]);
+            root.entries.insert(new_key, entry);
+        }
+
+        assert!(root.entries.get("/from").is_none());
+        assert!(matches!(root.entries.get("/to/from"), Some(Entry::Dir(_))));
+        assert!(matches!(
+            root.entries.get("/to/from/child"),
+            Some(Entry::Dir(_))
+        ));
+    }
+}
@@ -0,0 +1,957 @@
+//! A [`FileSystem`](super::FileSystem) backend persisted to a [`BackingStore`].
+//!
+//! Unlike [`in_mem`](super::in_mem), every mutation is written through to the store before
+//! returning. Durability and crash-consistency follow the dirstate-v2 "docket" technique: a pair
+//! of small, fixed-size docket blocks precede a data region split into two halves. A mutation
+//! serializes the whole tree into the *other* half (never the one the currently-published docket
+//! points at), then republishes by writing a fresh docket, with a bumped generation, into the
+//! *other* docket slot. A crash at any point before that final write leaves the previously
+//! published docket, and the data half it points at, completely untouched.
+//!
+//! This backend's on-disk entry model is deliberately simpler than [`in_mem`]'s in-memory one: no
+//! symlinks, no open-file append tracking beyond what's needed for `read`/`write`/`lseek`. `chmod`,
+//! `unlink`, `symlink`, `stat`, `readdir`, and `rename` are not yet implemented for this backend;
+//! calling them returns an `Unsupported`-style error rather than panicking.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+use thiserror::Error;
+
+use crate::path::Arg;
+use crate::sync;
+
+use super::backing_store::BackingStore;
+use super::errors::{
+    ChmodError, CloseError, MkdirError, OpenError, PathError, ReaddirError, ReadError,
+    RenameError, RmdirError, SeekError, StatError, SymlinkError, UnlinkError, WriteError,
+};
+use super::Mode;
+
+const DOCKET_MAGIC: [u8; 8] = *b"LBXDOCK1";
+const FORMAT_VERSION: u32 = 1;
+/// Number of blocks reserved for docket slots, at the very start of the store.
+const DOCKET_SLOT_COUNT: u64 = 2;
+/// Length, in bytes, of the portion of a docket block that is actually meaningful; the rest of
+/// the block is zero-padded.
+const DOCKET_HEADER_LEN: usize = 8 + 4 + 8 + 8 + 8 + 4;
+
+/// A backing implementation for [`FileSystem`](super::FileSystem) storing all files on a
+/// [`BackingStore`], surviving process restarts.
+pub struct FileSystem<'platform, Platform: sync::RawSyncPrimitivesProvider, Store: BackingStore> {
+    sync: sync::Synchronization<'platform, Platform>,
+    state: sync::RwLock<'platform, Platform, PersistedState<Store>>,
+    open_files: sync::RwLock<'platform, Platform, OpenFileTable>,
+    current_user: UserInfo,
+    // cwd invariant: always ends with a `/`
+    current_working_dir: String,
+}
+
+impl<'platform, Platform: sync::RawSyncPrimitivesProvider, Store: BackingStore>
+    FileSystem<'platform, Platform, Store>
+{
+    /// Opens a persistent filesystem backed by `store`, loading whichever docket slot holds the
+    /// higher valid generation. If neither slot holds a valid docket (e.g. a freshly-formatted
+    /// store), initializes an empty root directory and persists it before returning.
+    pub fn new(
+        platform: &'platform Platform,
+        store: Store,
+    ) -> Result<Self, LoadError<Store::Error>> {
+        let state = PersistedState::load_or_init(store)?;
+        let sync = sync::Synchronization::new(platform);
+        let state = sync.new_rwlock(state);
+        let open_files = sync.new_rwlock(OpenFileTable::new());
+        Ok(Self {
+            sync,
+            state,
+            open_files,
+            current_user: UserInfo {
+                user: 1000,
+                group: 1000,
+            },
+            current_working_dir: "/".into(),
+        })
+    }
+}
+
+impl<Platform: sync::RawSyncPrimitivesProvider, Store: BackingStore> super::private::Sealed
+    for FileSystem<'_, Platform, Store>
+{
+}
+
+impl<Platform: sync::RawSyncPrimitivesProvider, Store: BackingStore>
+    FileSystem<'_, Platform, Store>
+{
+    // Gives the absolute path for `path`, resolving any `.` or `..`s, and making sure to account
+    // for any relative paths from current working directory. Mirrors `in_mem`'s helper of the
+    // same name.
+    fn absolute_path(&self, path: impl crate::path::Arg) -> Result<String, PathError> {
+        assert!(self.current_working_dir.ends_with('/'));
+        Ok((self.current_working_dir.clone() + path.as_rust_str()?).normalized()?)
+    }
+}
+
+impl<Platform: sync::RawSyncPrimitivesProvider, Store: BackingStore> super::FileSystem
+    for FileSystem<'_, Platform, Store>
+{
+    fn open(
+        &self,
+        path: impl crate::path::Arg,
+        flags: super::OFlags,
+        mode: super::Mode,
+    ) -> Result<crate::fd::FileFd, OpenError> {
+        let path = self.absolute_path(path)?;
+        let mut state = self.state.write();
+        let (parent, entry) = state.root.parent_and_entry_mut(&path, self.current_user)?;
+        let access = AccessMode::from_flags(flags);
+        match entry {
+            Some(entry) => {
+                if flags.contains(super::OFlags::CREAT | super::OFlags::EXCL) {
+                    return Err(OpenError::AlreadyExists);
+                }
+                let file = match entry {
+                    Entry::File(file) => file,
+                    Entry::Dir(_) => return Err(OpenError::IsADirectory),
+                };
+                if access.can_read() && !self.current_user.can_read(&file.perms) {
+                    return Err(OpenError::NoReadPerms);
+                }
+                if access.can_write() && !self.current_user.can_write(&file.perms) {
+                    return Err(OpenError::NoWritePerms);
+                }
+                if flags.contains(super::OFlags::TRUNC) {
+                    file.data.clear();
+                }
+            }
+            None => {
+                if !flags.contains(super::OFlags::CREAT) {
+                    return Err(PathError::NoSuchFileOrDirectory)?;
+                }
+                let Some((_, parent_dir)) = parent else {
+                    // Attempted to create `/` as a file.
+                    return Err(OpenError::IsADirectory);
+                };
+                if !self.current_user.can_write(&parent_dir.perms) {
+                    return Err(OpenError::NoWritePerms);
+                }
+                parent_dir.children_count = parent_dir.children_count.checked_add(1).unwrap();
+                state.root.entries.insert(
+                    path.clone(),
+                    Entry::File(File {
+                        perms: Permissions {
+                            mode,
+                            userinfo: self.current_user,
+                        },
+                        data: Vec::new(),
+                    }),
+                );
+            }
+        }
+        if flags.contains(super::OFlags::CREAT) || flags.contains(super::OFlags::TRUNC) {
+            state
+                .persist()
+                .map_err(|e| OpenError::Store(format!("{e:?}")))?;
+        }
+        let offset = if flags.contains(super::OFlags::APPEND) {
+            let Some(Entry::File(file)) = state.root.entries.get(&path) else {
+                unreachable!("just inserted or confirmed a file at this path")
+            };
+            file.data.len() as u64
+        } else {
+            0
+        };
+        let mut open_files = self.open_files.write();
+        Ok(open_files.insert(OpenFile {
+            path,
+            offset,
+            access,
+            append: flags.contains(super::OFlags::APPEND),
+        }))
+    }
+
+    fn close(&self, fd: crate::fd::FileFd) -> Result<(), CloseError> {
+        let mut open_files = self.open_files.write();
+        open_files
+            .table
+            .remove(&fd.raw())
+            .ok_or(CloseError::BadFileDescriptor)?;
+        Ok(())
+    }
+
+    fn read(&self, fd: &crate::fd::FileFd, buf: &mut [u8]) -> Result<usize, ReadError> {
+        // Acquired in the same order as `open` (`state` before `open_files`) to avoid an AB-BA
+        // deadlock between a concurrent `open` and `read`/`write`/`lseek`.
+        let state = self.state.read();
+        let mut open_files = self.open_files.write();
+        let open_file = open_files
+            .table
+            .get_mut(&fd.raw())
+            .ok_or(ReadError::BadFileDescriptor)?;
+        if !open_file.access.can_read() {
+            return Err(ReadError::NotOpenForReading);
+        }
+        let Some(Entry::File(file)) = state.root.entries.get(&open_file.path) else {
+            return Err(ReadError::BadFileDescriptor);
+        };
+        let start = usize::try_from(open_file.offset).unwrap_or(usize::MAX);
+        let available = file.data.get(start..).unwrap_or(&[]);
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        open_file.offset += n as u64;
+        Ok(n)
+    }
+
+    fn write(&self, fd: &crate::fd::FileFd, buf: &[u8]) -> Result<usize, WriteError> {
+        // Acquired in the same order as `open` (`state` before `open_files`) to avoid an AB-BA
+        // deadlock between a concurrent `open` and `read`/`write`/`lseek`.
+        let mut state = self.state.write();
+        let mut open_files = self.open_files.write();
+        let open_file = open_files
+            .table
+            .get_mut(&fd.raw())
+            .ok_or(WriteError::BadFileDescriptor)?;
+        if !open_file.access.can_write() {
+            return Err(WriteError::NotOpenForWriting);
+        }
+        let Some(Entry::File(file)) = state.root.entries.get_mut(&open_file.path) else {
+            return Err(WriteError::BadFileDescriptor);
+        };
+        if open_file.append {
+            open_file.offset = file.data.len() as u64;
+        }
+        let start = usize::try_from(open_file.offset).unwrap_or(usize::MAX);
+        let end = start.checked_add(buf.len()).ok_or(WriteError::InvalidOffset)?;
+        if end > file.data.len() {
+            file.data.resize(end, 0);
+        }
+        file.data[start..end].copy_from_slice(buf);
+        open_file.offset = end as u64;
+        state
+            .persist()
+            .map_err(|e| WriteError::Store(format!("{e:?}")))?;
+        Ok(buf.len())
+    }
+
+    fn chmod(&self, _path: impl crate::path::Arg, _mode: super::Mode) -> Result<(), ChmodError> {
+        Err(ChmodError::Unsupported)
+    }
+
+    fn unlink(&self, _path: impl crate::path::Arg) -> Result<(), UnlinkError> {
+        Err(UnlinkError::Unsupported)
+    }
+
+    fn mkdir(&self, path: impl crate::path::Arg, mode: super::Mode) -> Result<(), MkdirError> {
+        let path = self.absolute_path(path)?;
+        let mut state = self.state.write();
+        let (parent, entry) = state.root.parent_and_entry_mut(&path, self.current_user)?;
+        let Some((_, parent)) = parent else {
+            // Attempted to make `/`
+            return Err(MkdirError::AlreadyExists);
+        };
+        let None = entry else {
+            return Err(MkdirError::AlreadyExists);
+        };
+        if !self.current_user.can_write(&parent.perms) {
+            return Err(MkdirError::NoWritePerms);
+        };
+        parent.children_count = parent.children_count.checked_add(1).unwrap();
+        state.root.entries.insert(
+            path,
+            Entry::Dir(Dir {
+                perms: Permissions {
+                    mode,
+                    userinfo: self.current_user,
+                },
+                children_count: 0,
+            }),
+        );
+        state
+            .persist()
+            .map_err(|e| MkdirError::Store(format!("{e:?}")))?;
+        Ok(())
+    }
+
+    fn rmdir(&self, path: impl crate::path::Arg) -> Result<(), RmdirError> {
+        let path = self.absolute_path(path)?;
+        let mut state = self.state.write();
+        let (parent, entry) = state.root.parent_and_entry_mut(&path, self.current_user)?;
+        let Some((_, parent)) = parent else {
+            // Attempted to remove `/`
+            return Err(RmdirError::Busy);
+        };
+        let Some(entry) = entry else {
+            return Err(PathError::NoSuchFileOrDirectory)?;
+        };
+        let Entry::Dir(dir) = entry else {
+            return Err(RmdirError::NotADirectory);
+        };
+        if dir.children_count > 0 {
+            return Err(RmdirError::NotEmpty);
+        }
+        if !self.current_user.can_write(&parent.perms) {
+            return Err(RmdirError::NoWritePerms);
+        }
+        parent.children_count = parent.children_count.checked_sub(1).unwrap();
+        let removed = state.root.entries.remove(&path).unwrap();
+        assert!(matches!(
+            removed,
+            Entry::Dir(Dir {
+                children_count: 0,
+                ..
+            })
+        ));
+        state
+            .persist()
+            .map_err(|e| RmdirError::Store(format!("{e:?}")))?;
+        Ok(())
+    }
+
+    fn symlink(
+        &self,
+        _target: impl crate::path::Arg,
+        _linkpath: impl crate::path::Arg,
+    ) -> Result<(), SymlinkError> {
+        Err(SymlinkError::Unsupported)
+    }
+
+    fn lseek(
+        &self,
+        fd: &crate::fd::FileFd,
+        whence: super::Whence,
+        offset: i64,
+    ) -> Result<u64, SeekError> {
+        // Acquired in the same order as `open` (`state` before `open_files`) to avoid an AB-BA
+        // deadlock between a concurrent `open` and `read`/`write`/`lseek`, even though only the
+        // `Whence::End` branch below actually needs `state`.
+        let state = self.state.read();
+        let mut open_files = self.open_files.write();
+        let open_file = open_files
+            .table
+            .get_mut(&fd.raw())
+            .ok_or(SeekError::BadFileDescriptor)?;
+        let base = match whence {
+            super::Whence::Set => 0,
+            super::Whence::Cur => open_file.offset,
+            super::Whence::End => {
+                let Some(Entry::File(file)) = state.root.entries.get(&open_file.path) else {
+                    return Err(SeekError::BadFileDescriptor);
+                };
+                file.data.len() as u64
+            }
+        };
+        let new_offset = base
+            .checked_add_signed(offset)
+            .ok_or(SeekError::InvalidOffset)?;
+        open_file.offset = new_offset;
+        Ok(new_offset)
+    }
+
+    fn stat(&self, _path: impl crate::path::Arg) -> Result<super::Metadata, StatError> {
+        Err(StatError::Unsupported)
+    }
+
+    fn readdir(&self, _path: impl crate::path::Arg) -> Result<Vec<super::DirEntry>, ReaddirError> {
+        Err(ReaddirError::Unsupported)
+    }
+
+    fn rename(
+        &self,
+        _from: impl crate::path::Arg,
+        _to: impl crate::path::Arg,
+    ) -> Result<(), RenameError> {
+        Err(RenameError::Unsupported)
+    }
+}
+
+/// Everything guarded by [`FileSystem`]'s `state` lock: the in-memory mirror of the tree, plus
+/// enough bookkeeping about the backing store to persist it.
+struct PersistedState<Store: BackingStore> {
+    store: Store,
+    root: RootDir,
+    /// Which of the two docket slots (and matching data half) currently holds the published
+    /// state.
+    active_slot: u64,
+    generation: u64,
+    /// Number of blocks in each of the two data halves.
+    half_block_count: u64,
+}
+
+impl<Store: BackingStore> PersistedState<Store> {
+    fn load_or_init(mut store: Store) -> Result<Self, LoadError<Store::Error>> {
+        let block_size = store.block_size();
+        if block_size < DOCKET_HEADER_LEN {
+            return Err(LoadError::Corrupt("block size too small to hold a docket"));
+        }
+        let block_count = store.block_count();
+        if block_count <= DOCKET_SLOT_COUNT {
+            return Err(LoadError::Corrupt(
+                "store has no room for a data region past the docket slots",
+            ));
+        }
+        let half_block_count = (block_count - DOCKET_SLOT_COUNT) / 2;
+
+        let mut best: Option<(u64, Docket)> = None;
+        for slot in 0..DOCKET_SLOT_COUNT {
+            let mut buf = vec![0u8; block_size];
+            store.read_block(slot, &mut buf).map_err(LoadError::Store)?;
+            let Some(docket) = Docket::decode(&buf) else {
+                continue;
+            };
+            let better = match &best {
+                None => true,
+                Some((_, current_best)) => docket.generation > current_best.generation,
+            };
+            if better {
+                best = Some((slot, docket));
+            }
+        }
+
+        let Some((active_slot, docket)) = best else {
+            let mut state = Self {
+                store,
+                root: RootDir::new(),
+                active_slot: 0,
+                generation: 0,
+                half_block_count,
+            };
+            state.persist().map_err(|e| match e {
+                PersistError::Store(e) => LoadError::Store(e),
+                PersistError::TooLarge { .. } => {
+                    LoadError::Corrupt("empty root directory does not fit in the configured data half")
+                }
+            })?;
+            return Ok(state);
+        };
+
+        let data_len = usize::try_from(docket.data_len)
+            .map_err(|_| LoadError::Corrupt("data region length does not fit in memory"))?;
+        let mut data = vec![0u8; data_len.div_ceil(block_size) * block_size];
+        for (i, chunk) in data.chunks_mut(block_size).enumerate() {
+            store
+                .read_block(docket.data_block + i as u64, chunk)
+                .map_err(LoadError::Store)?;
+        }
+        data.truncate(data_len);
+        if checksum(&data) != docket.checksum {
+            return Err(LoadError::Corrupt("data region checksum mismatch"));
+        }
+        let root = RootDir::deserialize(&data)
+            .ok_or(LoadError::Corrupt("data region is not a valid tree"))?;
+        Ok(Self {
+            store,
+            root,
+            active_slot,
+            generation: docket.generation,
+            half_block_count,
+        })
+    }
+
+    /// Serializes `self.root` into the data half *not* currently published, then republishes by
+    /// writing a fresh docket, with a bumped generation, into the *other* docket slot.
+    ///
+    /// If this is interrupted at any point, the previously published docket slot and data half
+    /// are untouched and remain entirely valid.
+    fn persist(&mut self) -> Result<(), PersistError<Store::Error>> {
+        let data = self.root.serialize();
+        let next_slot = 1 - self.active_slot;
+        let block_size = self.store.block_size();
+        let blocks_needed = (data.len() as u64).div_ceil(block_size as u64);
+        if blocks_needed > self.half_block_count {
+            // Writing this many blocks would run past this data half's boundary and into the
+            // other, currently-published half, corrupting the docket that's still valid.
+            return Err(PersistError::TooLarge {
+                blocks_needed,
+                half_block_count: self.half_block_count,
+            });
+        }
+        let data_block = DOCKET_SLOT_COUNT + next_slot * self.half_block_count;
+        for (i, chunk) in data.chunks(block_size).enumerate() {
+            let mut block = vec![0u8; block_size];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.store
+                .write_block(data_block + i as u64, &block)
+                .map_err(PersistError::Store)?;
+        }
+        let next_generation = self.generation + 1;
+        let docket = Docket {
+            generation: next_generation,
+            data_block,
+            data_len: data.len() as u64,
+            checksum: checksum(&data),
+        };
+        self.store
+            .write_block(next_slot, &docket.encode(block_size))
+            .map_err(PersistError::Store)?;
+        self.store.flush().map_err(PersistError::Store)?;
+        self.active_slot = next_slot;
+        self.generation = next_generation;
+        Ok(())
+    }
+}
+
+/// Errors from [`PersistedState::persist`].
+#[non_exhaustive]
+#[derive(Error, Debug)]
+enum PersistError<E: core::fmt::Debug> {
+    #[error("backing store I/O error: {0:?}")]
+    Store(E),
+    #[error(
+        "serialized tree needs {blocks_needed} blocks, which doesn't fit in a {half_block_count}-block data half"
+    )]
+    TooLarge {
+        blocks_needed: u64,
+        half_block_count: u64,
+    },
+}
+
+/// Errors from [`FileSystem::new`].
+#[non_exhaustive]
+#[derive(Error, Debug)]
+pub enum LoadError<E: core::fmt::Debug> {
+    #[error("backing store I/O error: {0:?}")]
+    Store(E),
+    #[error("on-disk data is corrupt: {0}")]
+    Corrupt(&'static str),
+}
+
+/// A docket: the fixed-size header published at the start of a docket slot, pointing at whichever
+/// data region is current as of `generation`.
+#[derive(Clone, Copy)]
+struct Docket {
+    generation: u64,
+    data_block: u64,
+    data_len: u64,
+    checksum: u32,
+}
+
+impl Docket {
+    fn encode(&self, block_size: usize) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(block_size);
+        buf.extend_from_slice(&DOCKET_MAGIC);
+        buf.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        buf.extend_from_slice(&self.generation.to_le_bytes());
+        buf.extend_from_slice(&self.data_block.to_le_bytes());
+        buf.extend_from_slice(&self.data_len.to_le_bytes());
+        buf.extend_from_slice(&self.checksum.to_le_bytes());
+        buf.resize(block_size, 0);
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < DOCKET_HEADER_LEN || buf[..8] != DOCKET_MAGIC {
+            return None;
+        }
+        if u32::from_le_bytes(buf[8..12].try_into().ok()?) != FORMAT_VERSION {
+            return None;
+        }
+        Some(Self {
+            generation: u64::from_le_bytes(buf[12..20].try_into().ok()?),
+            data_block: u64::from_le_bytes(buf[20..28].try_into().ok()?),
+            data_len: u64::from_le_bytes(buf[28..36].try_into().ok()?),
+            checksum: u32::from_le_bytes(buf[36..40].try_into().ok()?),
+        })
+    }
+}
+
+/// A simple, non-cryptographic checksum (FNV-1a) guarding against reading a torn or
+/// partially-written data region.
+fn checksum(data: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for &byte in data {
+        hash ^= u32::from(byte);
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}
+
+/// The on-disk directory tree: a flat map from normalized path to entry, same keying scheme as
+/// [`in_mem::RootDir`](super::in_mem), but without symlinks.
+struct RootDir {
+    entries: HashMap<String, Entry>,
+}
+
+// Parent, if it exists, is the path as well as the directory.
+// The entry, if it exists, is just the entry itself.
+type ParentAndEntry<'a, D, E> = Result<(Option<(&'a str, D)>, Option<E>), PathError>;
+
+impl RootDir {
+    fn new() -> Self {
+        Self {
+            entries: [(
+                String::new(),
+                Entry::Dir(Dir {
+                    perms: Permissions {
+                        mode: Mode::RWXU | Mode::RGRP | Mode::XGRP | Mode::ROTH | Mode::WOTH,
+                        userinfo: UserInfo { user: 0, group: 0 },
+                    },
+                    children_count: 0,
+                }),
+            )]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    fn parent_and_entry_mut(
+        &mut self,
+        path: &str,
+        current_user: UserInfo,
+    ) -> ParentAndEntry<&mut Dir, &mut Entry> {
+        let mut real_components_seen = false;
+        let mut collected = String::new();
+        let mut parent_path = None;
+        for p in path.normalized_components()? {
+            if p.is_empty() || p == ".." {
+                assert!(!real_components_seen);
+                continue;
+            }
+            real_components_seen = true;
+            match self
+                .entries
+                .get_mut(&collected)
+                .ok_or(PathError::MissingComponent)?
+            {
+                Entry::File(_) => return Err(PathError::ComponentNotADirectory),
+                Entry::Dir(dir) => {
+                    if !current_user.can_execute(&dir.perms) {
+                        return Err(PathError::NoSearchPerms);
+                    }
+                    parent_path = Some(collected.clone());
+                }
+            }
+            collected += "/";
+            collected += p;
+        }
+        if let Some(parent_path) = parent_path {
+            let [parent_path_and_entry, main_path_and_entry] = self
+                .entries
+                .get_many_key_value_mut([&parent_path, &collected]);
+            let (parent_path, parent_dir) = match parent_path_and_entry.unwrap() {
+                (_, Entry::File(_)) => unreachable!(),
+                (path, Entry::Dir(dir)) => (path, dir),
+            };
+            let main_entry = main_path_and_entry.map(|(_, e)| e);
+            Ok((Some((parent_path, parent_dir)), main_entry))
+        } else {
+            Ok((None, self.entries.get_mut(&collected)))
+        }
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for (path, entry) in &self.entries {
+            let path_bytes = path.as_bytes();
+            buf.extend_from_slice(&(path_bytes.len() as u32).to_le_bytes());
+            buf.extend_from_slice(path_bytes);
+            match entry {
+                Entry::Dir(dir) => {
+                    buf.push(0);
+                    buf.extend_from_slice(&dir.perms.mode.bits().to_le_bytes());
+                    buf.extend_from_slice(&dir.perms.userinfo.user.to_le_bytes());
+                    buf.extend_from_slice(&dir.perms.userinfo.group.to_le_bytes());
+                    buf.extend_from_slice(&dir.children_count.to_le_bytes());
+                }
+                Entry::File(file) => {
+                    buf.push(1);
+                    buf.extend_from_slice(&file.perms.mode.bits().to_le_bytes());
+                    buf.extend_from_slice(&file.perms.userinfo.user.to_le_bytes());
+                    buf.extend_from_slice(&file.perms.userinfo.group.to_le_bytes());
+                    buf.extend_from_slice(&(file.data.len() as u64).to_le_bytes());
+                    buf.extend_from_slice(&file.data);
+                }
+            }
+        }
+        buf
+    }
+
+    fn deserialize(buf: &[u8]) -> Option<Self> {
+        let mut cursor = 0usize;
+        let mut take = |len: usize| -> Option<&[u8]> {
+            let slice = buf.get(cursor..cursor + len)?;
+            cursor += len;
+            Some(slice)
+        };
+        let count = u32::from_le_bytes(take(4)?.try_into().ok()?);
+        let mut entries = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let path_len = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+            let path = String::from_utf8(take(path_len)?.to_vec()).ok()?;
+            let tag = *take(1)?.first()?;
+            let mode = Mode::from_bits_truncate(u32::from_le_bytes(take(4)?.try_into().ok()?));
+            let user = u16::from_le_bytes(take(2)?.try_into().ok()?);
+            let group = u16::from_le_bytes(take(2)?.try_into().ok()?);
+            let perms = Permissions {
+                mode,
+                userinfo: UserInfo { user, group },
+            };
+            let entry = match tag {
+                0 => {
+                    let children_count = u32::from_le_bytes(take(4)?.try_into().ok()?);
+                    Entry::Dir(Dir {
+                        perms,
+                        children_count,
+                    })
+                }
+                1 => {
+                    let data_len = u64::from_le_bytes(take(8)?.try_into().ok()?) as usize;
+                    let data = take(data_len)?.to_vec();
+                    Entry::File(File { perms, data })
+                }
+                _ => return None,
+            };
+            entries.insert(path, entry);
+        }
+        Some(Self { entries })
+    }
+}
+
+enum Entry {
+    Dir(Dir),
+    File(File),
+}
+
+struct Dir {
+    perms: Permissions,
+    children_count: u32,
+}
+
+struct File {
+    perms: Permissions,
+    data: Vec<u8>,
+}
+
+/// An open-file table entry, one per live [`FileFd`](crate::fd::FileFd). Mirrors
+/// [`in_mem::OpenFile`](super::in_mem).
+struct OpenFile {
+    path: String,
+    offset: u64,
+    access: AccessMode,
+    append: bool,
+}
+
+#[derive(Clone, Copy)]
+enum AccessMode {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl AccessMode {
+    fn from_flags(flags: super::OFlags) -> Self {
+        if flags.contains(super::OFlags::RDWR) {
+            Self::ReadWrite
+        } else if flags.contains(super::OFlags::WRONLY) {
+            Self::Write
+        } else {
+            Self::Read
+        }
+    }
+
+    fn can_read(self) -> bool {
+        matches!(self, Self::Read | Self::ReadWrite)
+    }
+
+    fn can_write(self) -> bool {
+        matches!(self, Self::Write | Self::ReadWrite)
+    }
+}
+
+struct OpenFileTable {
+    next_fd: u64,
+    table: HashMap<u64, OpenFile>,
+}
+
+impl OpenFileTable {
+    fn new() -> Self {
+        Self {
+            next_fd: 0,
+            table: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, open_file: OpenFile) -> crate::fd::FileFd {
+        let raw = self.next_fd;
+        self.next_fd = self
+            .next_fd
+            .checked_add(1)
+            .expect("file descriptor space exhausted");
+        self.table.insert(raw, open_file);
+        crate::fd::FileFd::from_raw(raw)
+    }
+}
+
+struct Permissions {
+    mode: Mode,
+    userinfo: UserInfo,
+}
+
+#[derive(Clone, Copy)]
+struct UserInfo {
+    user: u16,
+    group: u16,
+}
+
+impl UserInfo {
+    fn can_read(self, perms: &Permissions) -> bool {
+        perms.can_read_by(self)
+    }
+    fn can_write(self, perms: &Permissions) -> bool {
+        perms.can_write_by(self)
+    }
+    fn can_execute(self, perms: &Permissions) -> bool {
+        perms.can_execute_by(self)
+    }
+}
+
+impl Permissions {
+    fn can_read_by(&self, current: UserInfo) -> bool {
+        if self.userinfo.user == current.user {
+            self.mode.contains(Mode::RUSR)
+        } else if self.userinfo.group == current.group {
+            self.mode.contains(Mode::RGRP)
+        } else {
+            self.mode.contains(Mode::ROTH)
+        }
+    }
+    fn can_write_by(&self, current: UserInfo) -> bool {
+        if self.userinfo.user == current.user {
+            self.mode.contains(Mode::WUSR)
+        } else if self.userinfo.group == current.group {
+            self.mode.contains(Mode::WGRP)
+        } else {
+            self.mode.contains(Mode::WOTH)
+        }
+    }
+    fn can_execute_by(&self, current: UserInfo) -> bool {
+        if self.userinfo.user == current.user {
+            self.mode.contains(Mode::XUSR)
+        } else if self.userinfo.group == current.group {
+            self.mode.contains(Mode::XGRP)
+        } else {
+            self.mode.contains(Mode::XOTH)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `BackingStore` over plain in-memory blocks, standing in for a real device in tests.
+    struct MemStore {
+        block_size: usize,
+        blocks: Vec<Vec<u8>>,
+    }
+
+    impl MemStore {
+        fn new(block_size: usize, block_count: u64) -> Self {
+            Self {
+                block_size,
+                blocks: vec![vec![0u8; block_size]; block_count as usize],
+            }
+        }
+    }
+
+    impl BackingStore for MemStore {
+        type Error = core::convert::Infallible;
+
+        fn block_size(&self) -> usize {
+            self.block_size
+        }
+
+        fn block_count(&self) -> u64 {
+            self.blocks.len() as u64
+        }
+
+        fn read_block(&self, index: u64, buf: &mut [u8]) -> Result<(), Self::Error> {
+            buf.copy_from_slice(&self.blocks[index as usize]);
+            Ok(())
+        }
+
+        fn write_block(&mut self, index: u64, buf: &[u8]) -> Result<(), Self::Error> {
+            self.blocks[index as usize].copy_from_slice(buf);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    fn test_user() -> UserInfo {
+        UserInfo {
+            user: 1000,
+            group: 1000,
+        }
+    }
+
+    fn test_file(data: &[u8]) -> Entry {
+        Entry::File(File {
+            perms: Permissions {
+                mode: Mode::RUSR | Mode::WUSR,
+                userinfo: test_user(),
+            },
+            data: data.to_vec(),
+        })
+    }
+
+    #[test]
+    fn round_trip_persists_and_reloads() {
+        let store = MemStore::new(64, 16);
+        let mut state = PersistedState::load_or_init(store).unwrap();
+        state.root.entries.insert("/greeting".into(), test_file(b"hello"));
+        state.persist().unwrap();
+
+        let PersistedState { store, .. } = state;
+        let reloaded = PersistedState::load_or_init(store).unwrap();
+        let Some(Entry::File(file)) = reloaded.root.entries.get("/greeting") else {
+            panic!("expected file to survive reload");
+        };
+        assert_eq!(file.data, b"hello");
+    }
+
+    #[test]
+    fn interrupted_publish_leaves_old_docket_valid() {
+        let store = MemStore::new(64, 16);
+        let mut state = PersistedState::load_or_init(store).unwrap();
+        state.root.entries.insert("/a".into(), test_file(b"first"));
+        state.persist().unwrap();
+        let published_generation = state.generation;
+        let published_slot = state.active_slot;
+
+        // Simulate a crash partway through a second `persist`: the new data half gets written,
+        // but the crash happens before the republishing docket write lands, so the old docket
+        // (and the data half it points at) must still be the one `load_or_init` picks up.
+        let next_slot = 1 - state.active_slot;
+        let block_size = state.store.block_size();
+        let garbage_block = vec![0xAAu8; block_size];
+        let data_block = DOCKET_SLOT_COUNT + next_slot * state.half_block_count;
+        state.store.write_block(data_block, &garbage_block).unwrap();
+
+        let PersistedState { store, .. } = state;
+        let recovered = PersistedState::load_or_init(store).unwrap();
+        assert_eq!(recovered.generation, published_generation);
+        assert_eq!(recovered.active_slot, published_slot);
+        assert!(recovered.root.entries.contains_key("/a"));
+    }
+
+    #[test]
+    fn persist_rejects_tree_too_large_for_data_half() {
+        // 2 docket blocks + 2 data blocks per half, of 64 bytes each: one data half can't hold a
+        // file bigger than ~128 bytes once the serialization overhead is included.
+        let store = MemStore::new(64, 6);
+        let mut state = PersistedState::load_or_init(store).unwrap();
+        state
+            .root
+            .entries
+            .insert("/big".into(), test_file(&vec![0u8; 4096]));
+        match state.persist() {
+            Err(PersistError::TooLarge { .. }) => {}
+            other => panic!("expected PersistError::TooLarge, got {other:?}"),
+        }
+    }
+}
@@ -1,15 +1,25 @@
 //! Handling the allocation of local ports
 
-use core::num::{NonZeroU16, NonZeroU64};
+use core::num::{NonZeroU16, NonZeroU32, NonZeroU64};
 
-use hashbrown::HashSet;
+use hashbrown::HashMap;
 use thiserror::Error;
 
+use crate::platform;
 use crate::utilities::rng::FastRng;
 
+/// Seed used when no platform entropy source is available (e.g. a bare-metal platform with no RNG
+/// wired up yet). Picking ephemeral ports from this seed is still functionally correct, just
+/// predictable, so this is a fallback rather than a hard requirement.
+const FALLBACK_SEED: NonZeroU64 = NonZeroU64::new(0x13374a4159421337).unwrap();
+
 /// An allocator for local ports, making sure that no already-allocated ports are given out
+///
+/// Ports are reference-counted rather than simply present-or-absent, so that multiple sockets can
+/// legitimately share one local port (e.g. a listening socket and its accepted children, or
+/// `SO_REUSEPORT` load balancing) via [`LocalPortAllocator::allocate_same_local_port`].
 pub(crate) struct LocalPortAllocator {
-    allocated: HashSet<NonZeroU16>,
+    allocated: HashMap<NonZeroU16, NonZeroU32>,
     rng: FastRng,
 }
 
@@ -20,11 +30,26 @@ impl Default for LocalPortAllocator {
 }
 
 impl LocalPortAllocator {
-    /// Sets up a new local port allocator
+    /// Sets up a new local port allocator seeded with a fixed, deterministic seed.
+    ///
+    /// Prefer [`LocalPortAllocator::new_seeded`] wherever a platform entropy source is available:
+    /// a fixed seed means every process picks the exact same ephemeral-port sequence, which makes
+    /// off-path injection/guessing attacks against those ports easier.
     pub(crate) fn new() -> Self {
         Self {
-            allocated: HashSet::new(),
-            rng: FastRng::new_from_seed(NonZeroU64::new(0x13374a4159421337).unwrap()),
+            allocated: HashMap::new(),
+            rng: FastRng::new_from_seed(FALLBACK_SEED),
+        }
+    }
+
+    /// Sets up a new local port allocator, seeding it from `platform`'s entropy source so the
+    /// ephemeral-port sequence is unpredictable across processes. Falls back to the fixed seed
+    /// used by [`LocalPortAllocator::new`] if the platform has no entropy available.
+    pub(crate) fn new_seeded<Platform: platform::EntropyProvider>(platform: &Platform) -> Self {
+        let seed = platform.random_seed().unwrap_or(FALLBACK_SEED);
+        Self {
+            allocated: HashMap::new(),
+            rng: FastRng::new_from_seed(seed),
         }
     }
 
@@ -39,9 +64,14 @@ impl LocalPortAllocator {
             }
         }
         // If we haven't yet found a port after 100 tries, it is highly likely lots of ports are
-        // already in use, so we should start looking over them one by one
-        for port in 49152..=65535 {
-            let port = NonZeroU16::new(port).unwrap();
+        // already in use, so we should start looking over them one by one. Start from a random
+        // offset within the range and wrap around, rather than always 49152 first, so this
+        // fallback doesn't leak a deterministic scan order either.
+        const EPHEMERAL_RANGE: u32 = 65536 - 49152;
+        let start = self.rng.next_in_range_u32(0..EPHEMERAL_RANGE);
+        for i in 0..EPHEMERAL_RANGE {
+            let port = 49152 + (start + i) % EPHEMERAL_RANGE;
+            let port = NonZeroU16::new(u16::try_from(port).unwrap()).unwrap();
             if let Ok(local_port) = self.specific_port(port) {
                 return Ok(local_port);
             }
@@ -55,27 +85,47 @@ impl LocalPortAllocator {
         &mut self,
         port: NonZeroU16,
     ) -> Result<LocalPort, LocalPortAllocationError> {
-        if self.allocated.insert(port) {
-            Ok(LocalPort { port })
-        } else {
-            Err(LocalPortAllocationError::AlreadyInUse(port.get()))
+        match self.allocated.entry(port) {
+            hashbrown::hash_map::Entry::Vacant(entry) => {
+                entry.insert(NonZeroU32::new(1).unwrap());
+                Ok(LocalPort { port })
+            }
+            hashbrown::hash_map::Entry::Occupied(_) => {
+                Err(LocalPortAllocationError::AlreadyInUse(port.get()))
+            }
         }
     }
 
-    /// Increments the ref-count for a local port, producing a new `LocalPort` token to be used
+    /// Increments the ref-count for an already-allocated local port, producing a new `LocalPort`
+    /// token bound to the same port (e.g. for a listening socket's accepted children, or
+    /// `SO_REUSEPORT` load balancing).
     #[must_use]
     pub(crate) fn allocate_same_local_port(&mut self, port: &LocalPort) -> LocalPort {
-        // TODO(jayb): Definitely have to rethink this entire module now that I want this particular
-        // interface here.
-        todo!()
+        let count = self
+            .allocated
+            .get_mut(&port.port)
+            .expect("`LocalPort` tokens can only be produced for allocated ports");
+        *count = count.checked_add(1).expect("local port ref-count overflow");
+        LocalPort { port: port.port }
     }
 
     /// Marks a [`LocalPort`] as available again, consuming it
+    ///
+    /// Only actually frees the port (allowing it to be reallocated) once every outstanding token
+    /// bound to it has been deallocated.
     pub(crate) fn deallocate(&mut self, port: LocalPort) {
-        let was_removed = self.allocated.remove(&port.port);
-        // As an invariant, the only production of `LocalPort` can happen from here, thus it should
-        // be impossible to have a `LocalPort` containing a non-allocated spot.
-        assert!(was_removed);
+        let count = self
+            .allocated
+            .get_mut(&port.port)
+            // As an invariant, the only production of `LocalPort` can happen from here, thus it
+            // should be impossible to have a `LocalPort` containing a non-allocated spot.
+            .expect("`LocalPort` tokens can only be produced for allocated ports");
+        match NonZeroU32::new(count.get() - 1) {
+            Some(remaining) => *count = remaining,
+            None => {
+                self.allocated.remove(&port.port);
+            }
+        }
     }
 }
 
@@ -100,3 +150,89 @@ pub enum LocalPortAllocationError {
     #[error("No free ports are available")]
     NoAvailableFreePorts,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specific_port_rejects_a_second_allocation_of_the_same_port() {
+        let mut allocator = LocalPortAllocator::new();
+        let port = NonZeroU16::new(4000).unwrap();
+        let _first = allocator.specific_port(port).unwrap();
+        match allocator.specific_port(port) {
+            Err(LocalPortAllocationError::AlreadyInUse(4000)) => {}
+            Err(other) => panic!("expected AlreadyInUse(4000), got {other:?}"),
+            Ok(_) => panic!("expected AlreadyInUse(4000), got a fresh allocation"),
+        }
+    }
+
+    #[test]
+    fn a_port_is_only_freed_once_every_shared_token_is_deallocated() {
+        let mut allocator = LocalPortAllocator::new();
+        let port = NonZeroU16::new(4000).unwrap();
+        let first = allocator.specific_port(port).unwrap();
+        let second = allocator.allocate_same_local_port(&first);
+        assert_eq!(second.port(), 4000);
+
+        // Still referenced by `second`, so the port must stay unavailable.
+        allocator.deallocate(first);
+        match allocator.specific_port(port) {
+            Err(LocalPortAllocationError::AlreadyInUse(4000)) => {}
+            Err(other) => panic!("expected AlreadyInUse(4000), got {other:?}"),
+            Ok(_) => panic!("expected AlreadyInUse(4000), got a fresh allocation"),
+        }
+
+        // Last reference gone: now it can be reallocated.
+        allocator.deallocate(second);
+        assert!(allocator.specific_port(port).is_ok());
+    }
+
+    #[test]
+    fn ephemeral_ports_never_collide_with_an_already_allocated_port() {
+        let mut allocator = LocalPortAllocator::new();
+        let mut seen = alloc::vec::Vec::new();
+        for _ in 0..32 {
+            let port = allocator.ephemeral_port().unwrap();
+            assert!(!seen.contains(&port.port()));
+            seen.push(port.port());
+        }
+    }
+
+    struct NoEntropyPlatform;
+
+    impl platform::EntropyProvider for NoEntropyPlatform {
+        fn random_seed(&self) -> Option<NonZeroU64> {
+            None
+        }
+    }
+
+    struct FixedEntropyPlatform(NonZeroU64);
+
+    impl platform::EntropyProvider for FixedEntropyPlatform {
+        fn random_seed(&self) -> Option<NonZeroU64> {
+            Some(self.0)
+        }
+    }
+
+    #[test]
+    fn new_seeded_falls_back_to_the_fixed_seed_without_platform_entropy() {
+        let mut without_entropy = LocalPortAllocator::new_seeded(&NoEntropyPlatform);
+        let mut fallback_seeded = LocalPortAllocator::new();
+        assert_eq!(
+            without_entropy.ephemeral_port().unwrap().port(),
+            fallback_seeded.ephemeral_port().unwrap().port()
+        );
+    }
+
+    #[test]
+    fn new_seeded_with_platform_entropy_diverges_from_the_fallback_sequence() {
+        let seed = NonZeroU64::new(0xdead_beef_1234_5678).unwrap();
+        let mut entropy_seeded = LocalPortAllocator::new_seeded(&FixedEntropyPlatform(seed));
+        let mut fallback_seeded = LocalPortAllocator::new();
+        assert_ne!(
+            entropy_seeded.ephemeral_port().unwrap().port(),
+            fallback_seeded.ephemeral_port().unwrap().port()
+        );
+    }
+}
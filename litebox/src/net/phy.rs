@@ -8,12 +8,22 @@ use crate::platform;
 /// The maximum transmission unit for a device
 pub const DEVICE_MTU: usize = 1600;
 
-struct Device<Platform: platform::IPInterfaceProvider + 'static> {
+pub(crate) struct Device<Platform: platform::IPInterfaceProvider + 'static> {
     platform: &'static Platform,
     receive_buffer: [u8; DEVICE_MTU],
     send_buffer: [u8; DEVICE_MTU],
 }
 
+impl<Platform: platform::IPInterfaceProvider + 'static> Device<Platform> {
+    pub(crate) fn new(platform: &'static Platform) -> Self {
+        Self {
+            platform,
+            receive_buffer: [0; DEVICE_MTU],
+            send_buffer: [0; DEVICE_MTU],
+        }
+    }
+}
+
 impl<Platform: platform::IPInterfaceProvider + 'static> smoltcp::phy::Device for Device<Platform> {
     type RxToken<'a> = RxToken<'a>;
     type TxToken<'a> = TxToken<'a, Platform>;
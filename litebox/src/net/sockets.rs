@@ -0,0 +1,420 @@
+//! A TCP/UDP socket subsystem layered over [`Device`] and [`LocalPortAllocator`].
+//!
+//! This mirrors how hermit/unix lay out their `net` modules in std's `sys` tree: a thin adapter
+//! (here, [`Device`]) owns the raw link, and this module owns the protocol state machinery
+//! (`smoltcp`'s [`Interface`](smoltcp::iface::Interface) and
+//! [`SocketSet`](smoltcp::iface::SocketSet)) driven on top of it.
+
+use alloc::vec;
+
+use hashbrown::HashMap;
+use smoltcp::iface::{Config, Interface, SocketHandle, SocketSet};
+use smoltcp::socket::{tcp, udp};
+use smoltcp::time::Instant;
+use smoltcp::wire::{HardwareAddress, IpAddress};
+use thiserror::Error;
+
+use crate::platform;
+
+use super::local_ports::{LocalPort, LocalPortAllocationError, LocalPortAllocator};
+use super::phy::Device;
+
+const TCP_BUFFER_SIZE: usize = 8 * 1024;
+const UDP_BUFFER_SIZE: usize = 8 * 1024;
+const UDP_METADATA_CAPACITY: usize = 32;
+
+/// An opaque handle to an open socket, in the same spirit as [`FileFd`](crate::fd::FileFd): it
+/// indexes into this subsystem's own open-socket table rather than the filesystem's.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct SocketFd(u64);
+
+/// Whether a [`Sockets::socket`] call creates a TCP or UDP endpoint.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SocketKind {
+    Tcp,
+    Udp,
+}
+
+struct OpenSocket {
+    handle: SocketHandle,
+    kind: SocketKind,
+    local_port: Option<LocalPort>,
+    // The destination for `send` on a UDP socket, recorded by `connect` (`recv` still delivers
+    // datagrams from any peer). Unused for TCP, whose `smoltcp` socket already tracks its own peer.
+    remote: Option<(IpAddress, u16)>,
+}
+
+/// Owns the `smoltcp` interface and socket set driving a [`Device<Platform>`], and hands out
+/// [`SocketFd`] handles backed by [`LocalPortAllocator`]-managed local ports.
+pub(crate) struct Sockets<Platform: platform::IPInterfaceProvider + platform::EntropyProvider + 'static>
+{
+    device: Device<Platform>,
+    iface: Interface,
+    socket_set: SocketSet<'static>,
+    ports: LocalPortAllocator,
+    open: HashMap<u64, OpenSocket>,
+    next_fd: u64,
+}
+
+impl<Platform: platform::IPInterfaceProvider + platform::EntropyProvider + 'static>
+    Sockets<Platform>
+{
+    pub(crate) fn new(platform: &'static Platform, now: Instant) -> Self {
+        let mut device = Device::new(platform);
+        let config = Config::new(HardwareAddress::Ip);
+        let iface = Interface::new(config, &mut device, now);
+        Self {
+            device,
+            iface,
+            socket_set: SocketSet::new(vec![]),
+            // Seeded from the platform's entropy source so source ports aren't predictable.
+            ports: LocalPortAllocator::new_seeded(platform),
+            open: HashMap::new(),
+            next_fd: 0,
+        }
+    }
+
+    fn insert(&mut self, open_socket: OpenSocket) -> SocketFd {
+        let raw = self.next_fd;
+        self.next_fd = self
+            .next_fd
+            .checked_add(1)
+            .expect("socket descriptor space exhausted");
+        self.open.insert(raw, open_socket);
+        SocketFd(raw)
+    }
+
+    fn get_mut(&mut self, fd: &SocketFd) -> Result<&mut OpenSocket, SocketError> {
+        self.open.get_mut(&fd.0).ok_or(SocketError::BadFileDescriptor)
+    }
+
+    /// Creates a new, unbound TCP or UDP socket.
+    pub(crate) fn socket(&mut self, kind: SocketKind) -> SocketFd {
+        let handle = match kind {
+            SocketKind::Tcp => {
+                let rx = tcp::SocketBuffer::new(vec![0; TCP_BUFFER_SIZE]);
+                let tx = tcp::SocketBuffer::new(vec![0; TCP_BUFFER_SIZE]);
+                self.socket_set.add(tcp::Socket::new(rx, tx))
+            }
+            SocketKind::Udp => {
+                let rx = udp::PacketBuffer::new(
+                    vec![udp::PacketMetadata::EMPTY; UDP_METADATA_CAPACITY],
+                    vec![0; UDP_BUFFER_SIZE],
+                );
+                let tx = udp::PacketBuffer::new(
+                    vec![udp::PacketMetadata::EMPTY; UDP_METADATA_CAPACITY],
+                    vec![0; UDP_BUFFER_SIZE],
+                );
+                self.socket_set.add(udp::Socket::new(rx, tx))
+            }
+        };
+        self.insert(OpenSocket {
+            handle,
+            kind,
+            local_port: None,
+            remote: None,
+        })
+    }
+
+    /// Binds `fd` to `port`, or an ephemeral port if `port` is `0`, drawing from the shared
+    /// [`LocalPortAllocator`].
+    pub(crate) fn bind(&mut self, fd: &SocketFd, addr: IpAddress, port: u16) -> Result<(), SocketError> {
+        if self.get_mut(fd)?.local_port.is_some() {
+            // Re-binding without going through `shutdown` first would silently drop (and leak)
+            // the previously-reserved `LocalPort` token, since it has no `Drop` impl of its own.
+            return Err(SocketError::AlreadyBound);
+        }
+        let local_port = self.reserve_port(port)?;
+        let (handle, kind) = {
+            let open = self.get_mut(fd)?;
+            (open.handle, open.kind)
+        };
+        if kind == SocketKind::Udp {
+            let socket = self.socket_set.get_mut::<udp::Socket>(handle);
+            socket
+                .bind((addr, local_port.port()))
+                .map_err(|_| SocketError::AddressInUse)?;
+        }
+        self.get_mut(fd)?.local_port = Some(local_port);
+        Ok(())
+    }
+
+    /// Connects `fd` to `remote`, binding an ephemeral local port first if `fd` isn't bound yet.
+    ///
+    /// For a UDP socket this records `remote` as the destination for `send` (`recv` still delivers
+    /// datagrams from any peer); for a TCP socket it initiates the handshake.
+    pub(crate) fn connect(
+        &mut self,
+        fd: &SocketFd,
+        remote: (IpAddress, u16),
+    ) -> Result<(), SocketError> {
+        let (handle, kind, existing_port) = {
+            let open = self.get_mut(fd)?;
+            (open.handle, open.kind, open.local_port.take())
+        };
+        let newly_bound = existing_port.is_none();
+        let local_port = match existing_port {
+            Some(port) => port,
+            None => self.ports.ephemeral_port()?,
+        };
+        if kind == SocketKind::Tcp {
+            let cx = self.iface.context();
+            let socket = self.socket_set.get_mut::<tcp::Socket>(handle);
+            socket
+                .connect(cx, remote, local_port.port())
+                .map_err(|_| SocketError::ConnectionFailed)?;
+        } else if newly_bound {
+            self.socket_set
+                .get_mut::<udp::Socket>(handle)
+                .bind(local_port.port())
+                .map_err(|_| SocketError::AddressInUse)?;
+        }
+        let open = self.get_mut(fd)?;
+        open.local_port = Some(local_port);
+        open.remote = Some(remote);
+        Ok(())
+    }
+
+    /// Puts `fd` into the listening state on its already-bound local port.
+    pub(crate) fn listen(&mut self, fd: &SocketFd) -> Result<(), SocketError> {
+        let (handle, port) = {
+            let open = self.get_mut(fd)?;
+            let port = open.local_port.as_ref().ok_or(SocketError::NotBound)?.port();
+            (open.handle, port)
+        };
+        let socket = self.socket_set.get_mut::<tcp::Socket>(handle);
+        socket.listen(port).map_err(|_| SocketError::AlreadyListening)
+    }
+
+    /// Accepts a connection on a listening `fd`, returning [`SocketError::WouldBlock`] if none is
+    /// ready yet.
+    ///
+    /// `fd` keeps listening on its local port afterward: the now-established connection is handed
+    /// off to a brand new `SocketFd` (sharing the same local port via
+    /// [`LocalPortAllocator::allocate_same_local_port`]), while `fd`'s underlying socket is
+    /// replaced with a fresh one put back into the listening state, ready to accept again.
+    pub(crate) fn accept(&mut self, fd: &SocketFd) -> Result<SocketFd, SocketError> {
+        let established_handle = self.get_mut(fd)?.handle;
+        let socket = self.socket_set.get_mut::<tcp::Socket>(established_handle);
+        // `is_active` also holds mid-handshake (`SynReceived`), which is not yet established.
+        if !socket.is_active() || socket.state() == tcp::State::SynReceived {
+            return Err(SocketError::WouldBlock);
+        }
+
+        let port = self
+            .open
+            .get(&fd.0)
+            .and_then(|open| open.local_port.as_ref())
+            .ok_or(SocketError::NotBound)?
+            .port();
+        let new_local_port = {
+            let existing = self
+                .open
+                .get(&fd.0)
+                .and_then(|open| open.local_port.as_ref())
+                .expect("checked above");
+            self.ports.allocate_same_local_port(existing)
+        };
+
+        let rx = tcp::SocketBuffer::new(vec![0; TCP_BUFFER_SIZE]);
+        let tx = tcp::SocketBuffer::new(vec![0; TCP_BUFFER_SIZE]);
+        let new_listener_handle = self.socket_set.add(tcp::Socket::new(rx, tx));
+        self.socket_set
+            .get_mut::<tcp::Socket>(new_listener_handle)
+            .listen(port)
+            .map_err(|_| SocketError::AlreadyListening)?;
+        self.get_mut(fd)?.handle = new_listener_handle;
+
+        Ok(self.insert(OpenSocket {
+            handle: established_handle,
+            kind: SocketKind::Tcp,
+            local_port: Some(new_local_port),
+            remote: None,
+        }))
+    }
+
+    /// Sends `buf` on `fd`, returning the number of bytes accepted.
+    pub(crate) fn send(&mut self, fd: &SocketFd, buf: &[u8]) -> Result<usize, SocketError> {
+        let (handle, kind, remote) = {
+            let open = self.get_mut(fd)?;
+            (open.handle, open.kind, open.remote)
+        };
+        match kind {
+            SocketKind::Tcp => {
+                let socket = self.socket_set.get_mut::<tcp::Socket>(handle);
+                if !socket.can_send() {
+                    return Err(SocketError::WouldBlock);
+                }
+                socket.send_slice(buf).map_err(|_| SocketError::ConnectionFailed)
+            }
+            SocketKind::Udp => {
+                let endpoint = remote.ok_or(SocketError::NotConnected)?;
+                let socket = self.socket_set.get_mut::<udp::Socket>(handle);
+                if !socket.can_send() {
+                    return Err(SocketError::WouldBlock);
+                }
+                socket
+                    .send_slice(buf, endpoint)
+                    .map(|()| buf.len())
+                    .map_err(|_| SocketError::ConnectionFailed)
+            }
+        }
+    }
+
+    /// Receives into `buf` from `fd`, returning the number of bytes read.
+    pub(crate) fn recv(&mut self, fd: &SocketFd, buf: &mut [u8]) -> Result<usize, SocketError> {
+        let (handle, kind) = {
+            let open = self.get_mut(fd)?;
+            (open.handle, open.kind)
+        };
+        match kind {
+            SocketKind::Tcp => {
+                let socket = self.socket_set.get_mut::<tcp::Socket>(handle);
+                if !socket.can_recv() {
+                    return Err(SocketError::WouldBlock);
+                }
+                socket.recv_slice(buf).map_err(|_| SocketError::ConnectionFailed)
+            }
+            SocketKind::Udp => {
+                let socket = self.socket_set.get_mut::<udp::Socket>(handle);
+                let (n, _) = socket.recv_slice(buf).map_err(|_| SocketError::WouldBlock)?;
+                Ok(n)
+            }
+        }
+    }
+
+    /// Closes `fd`, tearing down the underlying `smoltcp` socket and releasing its local port.
+    pub(crate) fn shutdown(&mut self, fd: &SocketFd) -> Result<(), SocketError> {
+        let open = self.open.remove(&fd.0).ok_or(SocketError::BadFileDescriptor)?;
+        match open.kind {
+            SocketKind::Tcp => self.socket_set.get_mut::<tcp::Socket>(open.handle).close(),
+            SocketKind::Udp => self.socket_set.get_mut::<udp::Socket>(open.handle).close(),
+        }
+        self.socket_set.remove(open.handle);
+        if let Some(port) = open.local_port {
+            self.ports.deallocate(port);
+        }
+        Ok(())
+    }
+
+    /// Pumps the interface so a platform event loop can advance TCP/UDP state (retransmits,
+    /// timeouts, handshake progress, etc).
+    pub(crate) fn poll(&mut self, timestamp: Instant) -> bool {
+        self.iface
+            .poll(timestamp, &mut self.device, &mut self.socket_set)
+    }
+
+    fn reserve_port(&mut self, port: u16) -> Result<LocalPort, SocketError> {
+        match core::num::NonZeroU16::new(port) {
+            Some(port) => Ok(self.ports.specific_port(port)?),
+            None => Ok(self.ports.ephemeral_port()?),
+        }
+    }
+}
+
+/// Errors that can arise from the [`Sockets`] API.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub(crate) enum SocketError {
+    #[error("bad file descriptor")]
+    BadFileDescriptor,
+    #[error(transparent)]
+    Port(#[from] LocalPortAllocationError),
+    #[error("socket is not bound to a local port")]
+    NotBound,
+    #[error("socket has no remote peer to send to")]
+    NotConnected,
+    #[error("address already in use")]
+    AddressInUse,
+    #[error("connection could not be established")]
+    ConnectionFailed,
+    #[error("socket is already listening")]
+    AlreadyListening,
+    #[error("socket is already bound to a local port")]
+    AlreadyBound,
+    #[error("operation would block")]
+    WouldBlock,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestPlatform;
+
+    impl platform::IPInterfaceProvider for TestPlatform {
+        fn receive_ip_packet(&self, _buf: &mut [u8]) -> Result<usize, platform::ReceiveError> {
+            Err(platform::ReceiveError::WouldBlock)
+        }
+
+        fn send_ip_packet(&self, _buf: &[u8]) -> Result<(), core::convert::Infallible> {
+            Ok(())
+        }
+    }
+
+    impl platform::EntropyProvider for TestPlatform {
+        fn random_seed(&self) -> Option<core::num::NonZeroU64> {
+            None
+        }
+    }
+
+    fn test_sockets() -> Sockets<TestPlatform> {
+        static PLATFORM: TestPlatform = TestPlatform;
+        Sockets::new(&PLATFORM, Instant::from_millis(0))
+    }
+
+    #[test]
+    fn rebinding_an_already_bound_socket_is_rejected() {
+        let mut sockets = test_sockets();
+        let fd = sockets.socket(SocketKind::Udp);
+        sockets.bind(&fd, IpAddress::v4(127, 0, 0, 1), 4000).unwrap();
+        match sockets.bind(&fd, IpAddress::v4(127, 0, 0, 1), 4001) {
+            Err(SocketError::AlreadyBound) => {}
+            other => panic!("expected AlreadyBound, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn shutdown_releases_the_local_port_for_reuse() {
+        let mut sockets = test_sockets();
+        let fd = sockets.socket(SocketKind::Udp);
+        sockets.bind(&fd, IpAddress::v4(127, 0, 0, 1), 4000).unwrap();
+        sockets.shutdown(&fd).unwrap();
+
+        let other = sockets.socket(SocketKind::Udp);
+        assert!(sockets.bind(&other, IpAddress::v4(127, 0, 0, 1), 4000).is_ok());
+    }
+
+    #[test]
+    fn listen_without_binding_first_is_rejected() {
+        let mut sockets = test_sockets();
+        let fd = sockets.socket(SocketKind::Tcp);
+        match sockets.listen(&fd) {
+            Err(SocketError::NotBound) => {}
+            other => panic!("expected NotBound, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn accept_on_a_non_established_listener_would_block() {
+        let mut sockets = test_sockets();
+        let fd = sockets.socket(SocketKind::Tcp);
+        sockets.bind(&fd, IpAddress::v4(0, 0, 0, 0), 4000).unwrap();
+        sockets.listen(&fd).unwrap();
+        match sockets.accept(&fd) {
+            Err(SocketError::WouldBlock) => {}
+            other => panic!("expected WouldBlock, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn operations_on_a_closed_descriptor_report_bad_file_descriptor() {
+        let mut sockets = test_sockets();
+        let fd = sockets.socket(SocketKind::Udp);
+        sockets.shutdown(&fd).unwrap();
+        match sockets.bind(&fd, IpAddress::v4(127, 0, 0, 1), 4000) {
+            Err(SocketError::BadFileDescriptor) => {}
+            other => panic!("expected BadFileDescriptor, got {other:?}"),
+        }
+    }
+}